@@ -0,0 +1,328 @@
+// Renders reports as SVG charts, drawn locally so that they can be put in slides. Every group has a
+// bar of its allocation next to a bar of the time spent on it, or the groups are stacked into a bar
+// of the allocation and one of the time spent. A report of several days also gets a line chart of
+// the time spent on every group each day.
+
+use crate::report::{Report, ReportRow};
+use crate::style::{self, Level};
+use chrono::Duration;
+
+const WIDTH: usize = 640;
+const LABEL_WIDTH: usize = 180;
+const ROW_HEIGHT: usize = 30;
+const LINE_CHART_HEIGHT: usize = 200;
+
+// Colors of the groups in the stacked and line charts, repeated when there are more groups.
+static PALETTE: [&str; 8] = [
+    "#4e79a7", "#f28e2b", "#59a14f", "#e15759", "#76b7b2", "#edc948", "#b07aa1", "#ff9da7",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChartKind {
+    Bars,
+    Stacked,
+}
+
+impl ChartKind {
+    pub fn parse_from_config(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("bars") => Ok(ChartKind::Bars),
+            Some("stacked") => Ok(ChartKind::Stacked),
+            Some(other) => Err(format!(
+                "Unsupported value '{}' for aggregate.chart. Supported values are 'bars' and 'stacked'.\n",
+                other
+            )),
+        }
+    }
+}
+
+fn hours(duration: Duration) -> f64 {
+    duration.num_seconds() as f64 / 3600.0
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn color(index: usize) -> &'static str {
+    PALETTE[index % PALETTE.len()]
+}
+
+fn level_color(ratio: f64) -> &'static str {
+    match style::level(ratio) {
+        Level::Under => "#59a14f",
+        Level::Near => "#edc948",
+        Level::Over => "#e15759",
+    }
+}
+
+fn text(x: usize, y: usize, anchor: &str, content: &str) -> String {
+    format!(
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"{}\">{}</text>",
+        x,
+        y,
+        anchor,
+        escape(content)
+    )
+}
+
+fn rect(x: f64, y: usize, width: f64, height: usize, fill: &str) -> String {
+    format!(
+        "<rect x=\"{:.1}\" y=\"{}\" width=\"{:.1}\" height=\"{}\" fill=\"{}\"/>",
+        x, y, width, height, fill
+    )
+}
+
+// Returns the elements of a bar of the allocation and a bar of the time spent for every group.
+fn bar_chart(report: &Report, top: usize) -> (Vec<String>, usize) {
+    let scale = report
+        .rows
+        .iter()
+        .map(|row| hours(row.allocated).max(hours(row.spent)))
+        .fold(0.0, f64::max);
+    let bar_width = (WIDTH - LABEL_WIDTH - 100) as f64;
+    let mut elements = Vec::new();
+    let mut y = top;
+    for row in report.rows.iter() {
+        let width = |duration: Duration| {
+            if scale > 0.0 {
+                hours(duration).max(0.0) / scale * bar_width
+            } else {
+                0.0
+            }
+        };
+        elements.push(text(LABEL_WIDTH - 10, y + 18, "end", &row.name));
+        elements.push(rect(
+            LABEL_WIDTH as f64,
+            y + 4,
+            width(row.allocated),
+            10,
+            "#cccccc",
+        ));
+        elements.push(rect(
+            LABEL_WIDTH as f64,
+            y + 16,
+            width(row.spent),
+            10,
+            level_color(row.ratio()),
+        ));
+        elements.push(text(
+            LABEL_WIDTH + width(row.allocated.max(row.spent)) as usize + 6,
+            y + 20,
+            "start",
+            &format!("{:.1}h / {:.1}h", hours(row.spent), hours(row.allocated)),
+        ));
+        y += ROW_HEIGHT;
+    }
+    (elements, y)
+}
+
+// Returns the elements of a bar of the allocation and a bar of the time spent, each made of the
+// groups stacked, with a legend of the groups.
+fn stacked_chart(report: &Report, top: usize) -> (Vec<String>, usize) {
+    let scale = hours(report.total.allocated).max(hours(report.total.spent));
+    let bar_width = (WIDTH - LABEL_WIDTH - 100) as f64;
+    let mut elements = Vec::new();
+    let mut y = top;
+    for label in ["allocated", "spent"] {
+        let duration_of = |row: &ReportRow| {
+            if label == "allocated" {
+                row.allocated
+            } else {
+                row.spent
+            }
+        };
+        elements.push(text(LABEL_WIDTH - 10, y + 20, "end", label));
+        let mut x = LABEL_WIDTH as f64;
+        for (index, row) in report.rows.iter().enumerate() {
+            let width = if scale > 0.0 {
+                hours(duration_of(row)).max(0.0) / scale * bar_width
+            } else {
+                0.0
+            };
+            elements.push(rect(x, y + 4, width, 22, color(index)));
+            x += width;
+        }
+        let total = report.rows.iter().map(duration_of).map(hours).sum::<f64>();
+        elements.push(text(
+            x as usize + 6,
+            y + 20,
+            "start",
+            &format!("{:.1}h", total),
+        ));
+        y += ROW_HEIGHT + 6;
+    }
+    y = legend(report, &mut elements, y);
+    (elements, y)
+}
+
+fn legend(report: &Report, elements: &mut Vec<String>, top: usize) -> usize {
+    let mut y = top + 10;
+    for (index, row) in report.rows.iter().enumerate() {
+        elements.push(rect(LABEL_WIDTH as f64, y, 12.0, 12, color(index)));
+        elements.push(text(LABEL_WIDTH + 18, y + 11, "start", &row.name));
+        y += 18;
+    }
+    y
+}
+
+// Returns the elements of a line of the time spent on every group each day of the report.
+fn line_chart(report: &Report, top: usize) -> (Vec<String>, usize) {
+    let mut elements = Vec::new();
+    let max = report
+        .days
+        .iter()
+        .flat_map(|day| day.spent.iter())
+        .map(|spent| hours(*spent))
+        .fold(0.0, f64::max)
+        .max(1.0)
+        .ceil();
+    let left = 60;
+    let bottom = top + LINE_CHART_HEIGHT;
+    let step = (WIDTH - left - 40) as f64 / (report.days.len() - 1) as f64;
+    let y_of = |hours: f64| bottom as f64 - hours / max * (LINE_CHART_HEIGHT - 20) as f64;
+
+    elements.push(format!(
+        "<line x1=\"{0}\" y1=\"{1}\" x2=\"{2}\" y2=\"{1}\" stroke=\"#999999\"/>",
+        left,
+        bottom,
+        WIDTH - 40
+    ));
+    elements.push(text(
+        left - 6,
+        y_of(max) as usize + 4,
+        "end",
+        &format!("{}h", max),
+    ));
+    elements.push(text(left - 6, bottom + 4, "end", "0h"));
+    for (index, day) in report.days.iter().enumerate() {
+        let x = left + (index as f64 * step) as usize;
+        elements.push(text(
+            x,
+            bottom + 18,
+            "middle",
+            &day.date.format("%a %m-%d").to_string(),
+        ));
+    }
+    for index in 0..report.rows.len() {
+        let points: Vec<String> = report
+            .days
+            .iter()
+            .enumerate()
+            .map(|(day_index, day)| {
+                format!(
+                    "{:.1},{:.1}",
+                    left as f64 + day_index as f64 * step,
+                    y_of(hours(day.spent[index]))
+                )
+            })
+            .collect();
+        elements.push(format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+            points.join(" "),
+            color(index)
+        ));
+    }
+    let y = legend(report, &mut elements, bottom + 24);
+    (elements, y)
+}
+
+// Returns the lines of an SVG document with the charts of the reports one below the other.
+pub fn render_svg(reports: &[Report], kind: ChartKind) -> Vec<String> {
+    let mut elements = Vec::new();
+    let mut y = 10;
+    for report in reports {
+        if let Some(title) = &report.title {
+            y += 20;
+            elements.push(format!(
+                "<text x=\"10\" y=\"{}\" font-weight=\"bold\">{}</text>",
+                y,
+                escape(title)
+            ));
+        }
+        let (chart, bottom) = match kind {
+            ChartKind::Bars => bar_chart(report, y + 10),
+            ChartKind::Stacked => stacked_chart(report, y + 10),
+        };
+        elements.extend(chart);
+        y = bottom;
+        if report.days.len() > 1 {
+            let (chart, bottom) = line_chart(report, y + 20);
+            elements.extend(chart);
+            y = bottom;
+        }
+        y += 10;
+    }
+
+    let mut lines = vec![format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" font-family=\"sans-serif\" font-size=\"12\">",
+        WIDTH,
+        y + 10
+    )];
+    lines.push(format!(
+        "<rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>",
+        WIDTH,
+        y + 10
+    ));
+    lines.extend(elements);
+    lines.push("</svg>".to_string());
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render_svg, ChartKind};
+    use crate::report::{Day, Report};
+    use crate::testutil;
+    use chrono::{Duration, NaiveDate};
+
+    fn report() -> Report {
+        let s = "[{\"tags\": [\"office\", \"project\"], \"allocation\": 2}, {\"tags\": [\"office\", \"review\"], \"allocation\": 1}]";
+        let mut workgroups = testutil::workgroups(s);
+        workgroups[0].time_spent = Duration::hours(1);
+        workgroups[1].time_spent = Duration::hours(2);
+        Report::from_workgroups(None, &workgroups)
+    }
+
+    #[test]
+    fn render_bars() {
+        let lines = render_svg(&[report()], ChartKind::Bars);
+        assert!(lines[0].starts_with("<svg"));
+        assert_eq!(lines.last().unwrap(), "</svg>");
+        // The bars are scaled to the largest of them, the 2 hours spent on office review.
+        let rect = |y: usize, width: &str, fill: &str| {
+            format!(
+                "<rect x=\"180.0\" y=\"{}\" width=\"{}\" height=\"10\" fill=\"{}\"/>",
+                y, width, fill
+            )
+        };
+        assert!(lines.contains(&rect(24, "360.0", "#cccccc")));
+        assert!(lines.contains(&rect(36, "180.0", "#59a14f")));
+        assert!(lines.contains(&rect(66, "360.0", "#e15759")));
+        assert!(!lines.iter().any(|line| line.starts_with("<polyline")));
+    }
+
+    #[test]
+    fn render_stacked_and_daily() {
+        let mut report = report();
+        report.days = (0..7)
+            .map(|day| Day {
+                date: NaiveDate::from_ymd(2021, 7, 25) + Duration::days(day),
+                spent: vec![Duration::minutes(day * 10), Duration::zero()],
+            })
+            .collect();
+        let lines = render_svg(&[report], ChartKind::Stacked);
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("<polyline"))
+                .count(),
+            2
+        );
+        // The days start on the first day of the report, a Sunday here.
+        assert!(lines.iter().any(|line| line.contains(">Sun 07-25<")));
+        assert!(lines.iter().any(|line| line.contains(">3.0h<")));
+    }
+}
@@ -0,0 +1,70 @@
+// The clock used for intervals which are still going on. It is the system clock unless a moment to
+// replay is given with aggregate.now in the configuration or with the AGGREGATE_NOW environment
+// variable, in timewarrior's UTC format like 20210722T153000Z.
+
+use chrono::{Local, NaiveDateTime};
+use std::env;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    now: Option<NaiveDateTime>,
+}
+
+impl Clock {
+    pub fn system() -> Self {
+        Clock { now: None }
+    }
+
+    pub fn fixed(now: NaiveDateTime) -> Self {
+        Clock { now: Some(now) }
+    }
+
+    // Returns the clock overridden by the configured value if any, falling back to AGGREGATE_NOW
+    // and then to the system clock.
+    pub fn from_config(value: Option<&str>) -> Result<Self, String> {
+        let value = match value {
+            Some(val) => val.to_string(),
+            None => match env::var("AGGREGATE_NOW") {
+                Ok(val) => val,
+                Err(_) => return Ok(Clock::system()),
+            },
+        };
+        match NaiveDateTime::parse_from_str(&value, "%Y%m%dT%H%M%SZ") {
+            Ok(now) => Ok(Clock::fixed(now)),
+            Err(_) => Err(format!(
+                "Unable to parse '{}' as the current time. Expected a UTC time like 20210722T153000Z.\n",
+                value
+            )),
+        }
+    }
+
+    // Returns the current time in UTC.
+    pub fn now(&self) -> NaiveDateTime {
+        match self.now {
+            Some(val) => val,
+            None => Local::now().naive_utc(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Clock;
+    use chrono::NaiveDateTime;
+
+    #[test]
+    fn fixed() {
+        let now = NaiveDateTime::parse_from_str("20210722T153000Z", "%Y%m%dT%H%M%SZ").unwrap();
+        assert_eq!(Clock::fixed(now).now(), now);
+    }
+
+    #[test]
+    fn from_config() {
+        let clock = Clock::from_config(Some("20210722T153000Z")).unwrap();
+        assert_eq!(
+            clock.now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            "2021-07-22 15:30:00"
+        );
+        assert!(Clock::from_config(Some("yesterday")).is_err());
+    }
+}
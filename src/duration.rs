@@ -0,0 +1,148 @@
+// Formats durations in the reports, in the format set with aggregate.duration.format. Negative
+// durations, like the time remaining of a group over its allocation, have the sign shown once in
+// front of the value.
+
+use chrono::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DurationFormat {
+    // 2 hrs 15 mins
+    Words,
+    // 2:15
+    HoursMinutes,
+    // 2.25h
+    Decimal,
+    // 2h15m
+    Compact,
+    // The percentage of the allocation, like 75%.
+    Percentage,
+}
+
+impl DurationFormat {
+    pub fn parse_from_config(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("words") => Ok(DurationFormat::Words),
+            Some("hh:mm") => Ok(DurationFormat::HoursMinutes),
+            Some("decimal") => Ok(DurationFormat::Decimal),
+            Some("compact") => Ok(DurationFormat::Compact),
+            Some("percentage") => Ok(DurationFormat::Percentage),
+            Some(value) => Err(format!(
+                "Unsupported duration format '{}'. Supported formats are 'words', 'hh:mm', 'decimal', 'compact' and 'percentage'.\n",
+                value
+            )),
+        }
+    }
+
+    // Formats the duration, which is a part of the allocation for the percentage format.
+    pub fn format(&self, duration: Duration, allocated: Duration) -> String {
+        // A duration which rounds to zero is shown without the sign, like 0:00 and not -0:00.
+        let sign = |shown: bool| {
+            if shown && duration < Duration::zero() {
+                "-"
+            } else {
+                ""
+            }
+        };
+        let minutes = duration.num_minutes().abs();
+        let minutes_sign = sign(minutes != 0);
+        let (hours, minutes) = (minutes / 60, minutes % 60);
+        match self {
+            DurationFormat::Words => format!("{}{} hrs {} mins", minutes_sign, hours, minutes),
+            DurationFormat::HoursMinutes => format!("{}{}:{:02}", minutes_sign, hours, minutes),
+            DurationFormat::Decimal => {
+                let hours = format!("{:.2}", duration.num_seconds().abs() as f64 / 3600.0);
+                format!("{}{}h", sign(hours != "0.00"), hours)
+            }
+            DurationFormat::Compact => match (hours, minutes) {
+                (0, _) => format!("{}{}m", minutes_sign, minutes),
+                (_, 0) => format!("{}{}h", minutes_sign, hours),
+                _ => format!("{}{}h{}m", minutes_sign, hours, minutes),
+            },
+            DurationFormat::Percentage => {
+                if allocated.num_seconds() == 0 {
+                    return "-".to_string();
+                }
+                let percentage = format!(
+                    "{:.0}",
+                    duration.num_seconds().abs() as f64 * 100.0 / allocated.num_seconds() as f64
+                );
+                format!("{}{}%", sign(percentage != "0"), percentage)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DurationFormat;
+    use chrono::Duration;
+
+    #[test]
+    fn format() {
+        let duration = Duration::minutes(135);
+        let allocated = Duration::hours(3);
+        let format = |format: DurationFormat| format.format(duration, allocated);
+        assert_eq!(format(DurationFormat::Words), "2 hrs 15 mins");
+        assert_eq!(format(DurationFormat::HoursMinutes), "2:15");
+        assert_eq!(format(DurationFormat::Decimal), "2.25h");
+        assert_eq!(format(DurationFormat::Compact), "2h15m");
+        assert_eq!(format(DurationFormat::Percentage), "75%");
+        assert_eq!(
+            DurationFormat::Compact.format(Duration::hours(2), allocated),
+            "2h"
+        );
+        assert_eq!(
+            DurationFormat::Percentage.format(duration, Duration::zero()),
+            "-"
+        );
+    }
+
+    #[test]
+    fn format_negative() {
+        let duration = Duration::minutes(-66);
+        let allocated = Duration::hours(1);
+        let format = |format: DurationFormat| format.format(duration, allocated);
+        assert_eq!(format(DurationFormat::Words), "-1 hrs 6 mins");
+        assert_eq!(format(DurationFormat::HoursMinutes), "-1:06");
+        assert_eq!(format(DurationFormat::Decimal), "-1.10h");
+        assert_eq!(format(DurationFormat::Compact), "-1h6m");
+        assert_eq!(format(DurationFormat::Percentage), "-110%");
+        assert_eq!(
+            DurationFormat::Words.format(Duration::minutes(-3), allocated),
+            "-0 hrs 3 mins"
+        );
+    }
+
+    #[test]
+    fn format_negative_below_minute() {
+        let duration = Duration::seconds(-10);
+        let allocated = Duration::hours(1);
+        let format = |format: DurationFormat| format.format(duration, allocated);
+        assert_eq!(format(DurationFormat::Words), "0 hrs 0 mins");
+        assert_eq!(format(DurationFormat::HoursMinutes), "0:00");
+        assert_eq!(format(DurationFormat::Decimal), "0.00h");
+        assert_eq!(format(DurationFormat::Compact), "0m");
+        assert_eq!(format(DurationFormat::Percentage), "0%");
+        assert_eq!(
+            DurationFormat::Decimal.format(Duration::seconds(-30), allocated),
+            "-0.01h"
+        );
+        assert_eq!(
+            DurationFormat::HoursMinutes.format(Duration::minutes(-60), allocated),
+            "-1:00"
+        );
+    }
+
+    #[test]
+    fn parse_from_config() {
+        assert_eq!(
+            DurationFormat::parse_from_config(None).unwrap(),
+            DurationFormat::Words
+        );
+        assert_eq!(
+            DurationFormat::parse_from_config(Some("hh:mm")).unwrap(),
+            DurationFormat::HoursMinutes
+        );
+        assert!(DurationFormat::parse_from_config(Some("days")).is_err());
+    }
+}
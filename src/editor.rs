@@ -0,0 +1,378 @@
+// An interactive terminal editor for the plan of a day or a week. Time is shifted between groups in
+// steps of 15 minutes, from a source group to the selected group, so that the total stays the same.
+// The terminal is put in raw mode with stty and drawn with plain ANSI escape sequences. Durations
+// and the layout of the table follow the style of the reports.
+
+use crate::plan::Period;
+use crate::standalone::Settings;
+use crate::style::{self, Style};
+use crate::table::{Cell, Table};
+use crate::twdb;
+use crate::workgroup::{self, WeekLayout, WorkGroup};
+use chrono::Duration;
+use isatty::{stdin_isatty, stdout_isatty};
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+static USAGE: &str = "Usage: aggregate edit [<day or week>] [--week] [rc.<name>=<value> ...]
+The day or week is given the same way as for the copy command and defaults to :day.
+";
+
+static HELP: &str =
+    "up/down select  space make source  right move 15 mins to selected  left move back  s save  q quit";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Save,
+    Quit,
+    Other,
+}
+
+// Parses the keys in the bytes read from a terminal in raw mode.
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let key = match &bytes[index..] {
+            [0x1b, b'[', b'A', ..] => Key::Up,
+            [0x1b, b'[', b'B', ..] => Key::Down,
+            [0x1b, b'[', b'C', ..] => Key::Right,
+            [0x1b, b'[', b'D', ..] => Key::Left,
+            [b'k', ..] => Key::Up,
+            [b'j', ..] => Key::Down,
+            [b'l', ..] => Key::Right,
+            [b'h', ..] => Key::Left,
+            [b' ', ..] => Key::Space,
+            [b's', ..] => Key::Save,
+            // Ctrl-C doesn't interrupt in raw mode, so it quits like q.
+            [b'q', ..] | [0x03, ..] => Key::Quit,
+            _ => Key::Other,
+        };
+        index += match (&bytes[index..], key) {
+            ([0x1b, b'[', ..], Key::Up | Key::Down | Key::Left | Key::Right) => 3,
+            _ => 1,
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Continue,
+    Save,
+    Quit,
+}
+
+pub struct Editor {
+    pub workgroups: Vec<WorkGroup>,
+    pub selected: usize,
+    // The group time is taken from when moving time to the selected group.
+    pub source: usize,
+    pub style: Style,
+}
+
+impl Editor {
+    pub fn new(workgroups: Vec<WorkGroup>, style: Style) -> Self {
+        Editor {
+            workgroups,
+            selected: 0,
+            source: 0,
+            style,
+        }
+    }
+
+    fn shift(&mut self, from: usize, to: usize) {
+        let step = Duration::minutes(15);
+        if from == to || self.workgroups[from].time_allocated < step {
+            return;
+        }
+        self.workgroups[from].time_allocated = self.workgroups[from].time_allocated - step;
+        self.workgroups[to].time_allocated = self.workgroups[to].time_allocated + step;
+    }
+
+    pub fn handle(&mut self, key: Key) -> Action {
+        match key {
+            Key::Up if self.selected > 0 => self.selected -= 1,
+            Key::Down if self.selected + 1 < self.workgroups.len() => self.selected += 1,
+            Key::Space => self.source = self.selected,
+            Key::Right => self.shift(self.source, self.selected),
+            Key::Left => self.shift(self.selected, self.source),
+            Key::Save => return Action::Save,
+            Key::Quit => return Action::Quit,
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    pub fn total(&self) -> Duration {
+        self.workgroups
+            .iter()
+            .fold(Duration::seconds(0), |total, workgroup| {
+                total + workgroup.time_allocated
+            })
+    }
+
+    // Returns the lines to draw, the selected group is marked with '>' and the source with '*'. The
+    // table is laid out like the table of the report.
+    pub fn render(&self, title: &str) -> Vec<String> {
+        let mut table = Table::new(&["   group", "spent", "allocated"]);
+        for (index, workgroup) in self.workgroups.iter().enumerate() {
+            let selected = if index == self.selected { '>' } else { ' ' };
+            let source = if index == self.source { '*' } else { ' ' };
+            let (spent, allocated) = (workgroup.time_spent, workgroup.time_allocated);
+            table.push(vec![
+                Cell::new(&format!("{}{} {}", selected, source, workgroup.name())),
+                Cell::colored(
+                    &self.style.duration.format(spent, allocated),
+                    style::ratio(spent, allocated),
+                ),
+                Cell::new(&self.style.duration.format(allocated, allocated)),
+            ]);
+        }
+        let total = self.total();
+        table.push(vec![
+            Cell::new("   total"),
+            Cell::new(""),
+            Cell::new(&self.style.duration.format(total, total)),
+        ]);
+
+        let mut lines = vec![title.to_string(), String::new()];
+        lines.extend(table.render(&self.style, true));
+        lines.push(String::new());
+        lines.push(HELP.to_string());
+        lines
+    }
+}
+
+fn stty(args: &[&str]) -> Result<String, String> {
+    // stty works on the terminal of its standard input.
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        _ => Err("Unable to change the mode of the terminal with stty\n".to_string()),
+    }
+}
+
+fn draw(lines: &[String]) {
+    // Raw mode doesn't turn a newline into a carriage return and a newline.
+    print!("\x1b[2J\x1b[H{}\r\n", lines.join("\r\n"));
+    io::stdout().flush().unwrap();
+}
+
+// Keeps the terminal in raw mode while it's alive and restores its previous mode when dropped,
+// which also happens on an error or a panic.
+struct RawMode {
+    saved_mode: String,
+}
+
+impl RawMode {
+    fn enter() -> Result<Self, String> {
+        let saved_mode = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        Ok(RawMode { saved_mode })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if let Err(err) = stty(&[&self.saved_mode]) {
+            eprint!("{}", err);
+        }
+        print!("\x1b[2J\x1b[H");
+        let _ = io::stdout().flush();
+    }
+}
+
+// Runs the editor on the terminal until the plan is saved or the editor is quit, and returns
+// whether the plan was saved.
+fn run(editor: &mut Editor, title: &str) -> Result<bool, String> {
+    let _raw_mode = RawMode::enter()?;
+    let mut buffer = [0; 16];
+    let mut action = Action::Continue;
+    while action == Action::Continue {
+        draw(&editor.render(title));
+        let count = match io::stdin().read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(val) => val,
+        };
+        for key in parse_keys(&buffer[..count]) {
+            action = editor.handle(key);
+            if action != Action::Continue {
+                break;
+            }
+        }
+    }
+    Ok(action == Action::Save)
+}
+
+// Edits the plan of the day or week given in the arguments and returns the path it was saved to.
+pub fn edit_plan(args: &[String]) -> Result<Option<String>, String> {
+    if !stdin_isatty() || !stdout_isatty() {
+        return Err("The editor needs to be run in a terminal.\n".to_string());
+    }
+    let (settings, rest) = Settings::parse_from_args(args)?;
+    let week = rest.contains(&"--week");
+    let period = match rest.iter().find(|arg| **arg != "--week") {
+        Some(arg) => Period::parse_from_str(arg, settings.today(), settings.week_start, week)?,
+        None if week => {
+            Period::parse_from_str(":week", settings.today(), settings.week_start, week)?
+        }
+        None => Period::parse_from_str(":day", settings.today(), settings.week_start, week)?,
+    };
+    if rest.iter().filter(|arg| **arg != "--week").count() > 1 {
+        return Err(USAGE.to_string());
+    }
+
+    let layout = WeekLayout::parse_from_config(
+        settings
+            .config
+            .get("aggregate.week.layout")
+            .map(|val| val.as_str()),
+    )?;
+    let paths = period.allocation_file_paths(&workgroup::config_dir(), layout);
+    let (path, contents) = match workgroup::read_first_existing(&paths) {
+        Some(val) => val,
+        None => {
+            return Err(format!(
+                "Unable to open the workgroups definition file at {}\nUse 'aggregate copy' or 'aggregate suggest-plan' to create one.\n",
+                paths.join(" or ")
+            ))
+        }
+    };
+    let mut workgroups = workgroup::parse_allocation(&path, &contents)?;
+
+    // The time spent so far is shown along with the plan, when the database is there.
+    let (start, end) = match period {
+        Period::Day(day) => (day, day + Duration::days(1)),
+        Period::Week(week_start) => (week_start, week_start + Duration::days(7)),
+    };
+    if let Ok(twentries) = twdb::read_entries(&twdb::db_dir(), &settings.clock) {
+        let twinput = settings.input_for_range(start, end, &twentries);
+        workgroup::process(&twinput, &mut workgroups);
+    }
+
+    let style = Style::for_terminal(&settings.config)?;
+    let mut editor = Editor::new(workgroups, style);
+    let title = format!("Plan at {}", path);
+    if !run(&mut editor, &title)? {
+        return Ok(None);
+    }
+    let contents = workgroup::with_allocations(&contents, &editor.workgroups);
+    workgroup::write_allocation_file(std::slice::from_ref(&path), &contents, true)?;
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_keys, Action, Editor, Key};
+    use crate::duration::DurationFormat;
+    use crate::style::Style;
+    use crate::testutil;
+
+    fn editor() -> Editor {
+        let s = "[{\"tags\": [\"office\", \"project\"], \"allocation\": 2}, {\"tags\": [\"office\", \"review\"], \"allocation\": 0.25}, {\"tags\": [\"personal\"], \"allocation\": 1}]";
+        Editor::new(testutil::workgroups(s), Style::plain())
+    }
+
+    #[test]
+    fn parse_keys_arrows() {
+        let keys = parse_keys(b"\x1b[A\x1b[Bj \x1b[C\x1b[Dsq\x1b");
+        assert_eq!(
+            keys,
+            vec![
+                Key::Up,
+                Key::Down,
+                Key::Down,
+                Key::Space,
+                Key::Right,
+                Key::Left,
+                Key::Save,
+                Key::Quit,
+                Key::Other
+            ]
+        );
+    }
+
+    #[test]
+    fn handle_keeps_total() {
+        let mut editor = editor();
+        let total = editor.total();
+
+        // Move 30 minutes from office project to personal.
+        editor.handle(Key::Down);
+        editor.handle(Key::Down);
+        editor.handle(Key::Right);
+        editor.handle(Key::Right);
+        assert_eq!(editor.workgroups[0].time_allocated.num_minutes(), 90);
+        assert_eq!(editor.workgroups[2].time_allocated.num_minutes(), 90);
+        assert_eq!(editor.total(), total);
+
+        // Office review can only give away the 15 minutes it has.
+        editor.handle(Key::Up);
+        editor.handle(Key::Space);
+        editor.handle(Key::Down);
+        editor.handle(Key::Right);
+        editor.handle(Key::Right);
+        assert_eq!(editor.workgroups[1].time_allocated.num_minutes(), 0);
+        assert_eq!(editor.workgroups[2].time_allocated.num_minutes(), 105);
+        assert_eq!(editor.total(), total);
+
+        editor.handle(Key::Left);
+        assert_eq!(editor.workgroups[1].time_allocated.num_minutes(), 15);
+        assert_eq!(editor.total(), total);
+    }
+
+    #[test]
+    fn handle_stays_within_groups() {
+        let mut editor = editor();
+        editor.handle(Key::Up);
+        assert_eq!(editor.selected, 0);
+        for _ in 0..5 {
+            editor.handle(Key::Down);
+        }
+        assert_eq!(editor.selected, 2);
+        assert_eq!(editor.handle(Key::Save), Action::Save);
+        assert_eq!(editor.handle(Key::Quit), Action::Quit);
+    }
+
+    #[test]
+    fn render() {
+        let mut editor = editor();
+        editor.handle(Key::Down);
+        let lines = editor.render("Plan");
+        assert_eq!(lines[2], "|    group          | spent        | allocated");
+        assert_eq!(
+            lines[3],
+            "|  * office project | 0 hrs 0 mins | 2 hrs 0 mins"
+        );
+        assert_eq!(
+            lines[4],
+            "| >  office review  | 0 hrs 0 mins | 0 hrs 15 mins"
+        );
+        assert_eq!(
+            lines[6],
+            "|    total          |              | 3 hrs 15 mins"
+        );
+    }
+
+    #[test]
+    fn render_style() {
+        let mut editor = editor();
+        editor.style.duration = DurationFormat::HoursMinutes;
+        editor.style.width = Some(30);
+        let lines = editor.render("Plan");
+        assert_eq!(lines[3], "| >* offi… | 0:00  | 2:00");
+        assert_eq!(lines[6], "|    total |       | 3:15");
+    }
+}
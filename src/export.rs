@@ -0,0 +1,100 @@
+// Reads the intervals from the output of 'timew export', which is a single JSON array of the same
+// objects timewarrior passes to extensions. This allows aggregating archived exports or exports of
+// someone else without a timewarrior database.
+
+use crate::clock::Clock;
+use crate::twentry::TimeWarriorEntry;
+use std::fs;
+use std::io::{self, Read};
+
+pub fn parse_export(s: &str, clock: &Clock) -> Result<Vec<TimeWarriorEntry>, String> {
+    let parsed_json = match json::parse(s) {
+        Ok(val) => val,
+        Err(err) => return Err(format!("Unable to parse the export\nError: '{}'\n", err)),
+    };
+    if !parsed_json.is_array() {
+        return Err("The export is expected to be a JSON array of intervals\n".to_string());
+    }
+    let mut twentries = Vec::new();
+    for jv in parsed_json.members() {
+        match TimeWarriorEntry::parse_from_json_value(jv, clock) {
+            Ok(twentry) => twentries.push(twentry),
+            Err(problem) => {
+                return Err(format!(
+                    "Invalid interval in the export, {}: {}\n",
+                    problem,
+                    jv.dump()
+                ))
+            }
+        }
+    }
+    Ok(twentries)
+}
+
+// Reads the export at the given path, '-' being the standard input.
+pub fn read_export(path: &str, clock: &Clock) -> Result<Vec<TimeWarriorEntry>, String> {
+    let contents = if path == "-" {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer).map(|_| buffer)
+    } else {
+        fs::read_to_string(path)
+    };
+    let contents = match contents {
+        Ok(val) => val,
+        Err(err) => {
+            return Err(format!(
+                "Unable to read the export {}\nError: '{}'\n",
+                path, err
+            ))
+        }
+    };
+    match parse_export(&contents, clock) {
+        Ok(val) => Ok(val),
+        Err(err) => Err(format!("{}: {}", path, err)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_export;
+    use crate::clock::Clock;
+
+    #[test]
+    fn parse_export_array() {
+        let s = "[
+{\"id\":2,\"start\":\"20210722T152328Z\",\"end\":\"20210722T153753Z\",\"tags\":[\"learning\",\"personal\"]},
+{\"id\":1,\"start\":\"20210722T160000Z\",\"tags\":[\"office\",\"project\"]}
+]";
+        let twentries = parse_export(s, &Clock::system()).unwrap();
+
+        assert_eq!(twentries.len(), 2);
+        assert_eq!(twentries[0].duration().num_seconds(), 865);
+        assert!(twentries[1].tagset.tags.contains("project"));
+    }
+
+    #[test]
+    fn parse_export_invalid() {
+        assert!(parse_export("{\"start\":\"20210722T152328Z\"}", &Clock::system()).is_err());
+        assert!(parse_export("[{\"id\":1}]", &Clock::system()).is_err());
+        assert!(parse_export("[{\"id\":1},", &Clock::system()).is_err());
+    }
+
+    #[test]
+    fn parse_export_invalid_times() {
+        let clock = Clock::system();
+        let err = parse_export("[{\"start\":\"yesterday\"}]", &clock).unwrap_err();
+        assert!(err.starts_with("Invalid interval in the export, the start is missing"));
+        let err = parse_export(
+            "[{\"start\":\"20210722T152328Z\",\"end\":\"20210722\"}]",
+            &clock,
+        )
+        .unwrap_err();
+        assert!(err.starts_with("Invalid interval in the export, the end isn't a time"));
+        assert!(parse_export("[{\"start\":\"20210722T152328Z\",\"end\":1}]", &clock).is_err());
+        assert!(parse_export(
+            "[{\"start\":\"20210722T152328Z\",\"tags\":[\"office\",2]}]",
+            &clock
+        )
+        .is_err());
+    }
+}
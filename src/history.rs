@@ -0,0 +1,245 @@
+// A local store of the results of finished days and weeks, so that trends can be reported without
+// going through months of intervals again. The results are stored as JSON lines in
+// ~/.timewarrior/aggregate/history/results.jsonl, one line per range. Recording is turned off by
+// setting aggregate.history to off.
+
+use crate::tagset::TagSet;
+use crate::timezone;
+use crate::twinput::TimeWarriorInput;
+use crate::workgroup::{self, WorkGroup};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use json::{object, JsonValue};
+use log::debug;
+use std::fs;
+use std::path::PathBuf;
+
+pub struct HistoryRecord {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub workgroups: Vec<WorkGroup>,
+}
+
+impl HistoryRecord {
+    pub fn parse_from_json_value(jv: &JsonValue) -> Result<Self, String> {
+        let parse_date = |value: &JsonValue| {
+            value
+                .as_str()
+                .and_then(|value| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok())
+        };
+        let (start, end) = match (parse_date(&jv["start"]), parse_date(&jv["end"])) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Err("the start or the end is missing or invalid".to_string()),
+        };
+        let mut workgroups = Vec::new();
+        for group in jv["groups"].members() {
+            let (spent, allocated) = match (group["spent"].as_i64(), group["allocated"].as_i64()) {
+                (Some(spent), Some(allocated)) => (spent, allocated),
+                _ => return Err("a group is missing the time spent or allocated".to_string()),
+            };
+            let mut workgroup = WorkGroup::new(
+                TagSet::parse_from_json_value(group),
+                Duration::seconds(allocated),
+            );
+            workgroup.time_spent = Duration::seconds(spent);
+            workgroups.push(workgroup);
+        }
+        Ok(HistoryRecord {
+            start,
+            end,
+            workgroups,
+        })
+    }
+
+    pub fn to_json_value(&self) -> JsonValue {
+        let mut groups = JsonValue::new_array();
+        for workgroup in self.workgroups.iter() {
+            let group = object! {
+                tags: workgroup.tagset.sorted_tags(),
+                spent: workgroup.time_spent.num_seconds(),
+                allocated: workgroup.time_allocated.num_seconds(),
+            };
+            groups.push(group).unwrap();
+        }
+        object! {
+            start: self.start.format("%Y-%m-%d").to_string(),
+            end: self.end.format("%Y-%m-%d").to_string(),
+            groups: groups,
+        }
+    }
+}
+
+pub fn history_path() -> PathBuf {
+    PathBuf::from(workgroup::config_dir())
+        .join("history")
+        .join("results.jsonl")
+}
+
+// Parses the records, one per line. The lines which can't be parsed are returned separately along
+// with the reason, so that a single corrupt line doesn't lose the whole history.
+pub fn parse_records(s: &str) -> (Vec<HistoryRecord>, Vec<(String, String)>) {
+    let mut records = Vec::new();
+    let mut invalid = Vec::new();
+    for line in s.lines().filter(|line| !line.trim().is_empty()) {
+        let record = match json::parse(line) {
+            Ok(jv) => HistoryRecord::parse_from_json_value(&jv),
+            Err(err) => Err(err.to_string()),
+        };
+        match record {
+            Ok(val) => records.push(val),
+            Err(err) => invalid.push((line.to_string(), err)),
+        }
+    }
+    (records, invalid)
+}
+
+// Returns the recorded results sorted by the start of their range, along with the lines which
+// can't be parsed. These are reported and otherwise left alone. A missing store has no records.
+pub fn read_records() -> (Vec<HistoryRecord>, Vec<String>) {
+    let path = history_path();
+    let contents = match fs::read_to_string(&path) {
+        Ok(val) => val,
+        Err(_) => return (Vec::new(), Vec::new()),
+    };
+    let (records, invalid) = parse_records(&contents);
+    let mut lines = Vec::new();
+    for (line, err) in invalid {
+        eprintln!(
+            "Skipping the invalid history record '{}' in {}\nError: '{}'",
+            line,
+            path.display(),
+            err
+        );
+        lines.push(line);
+    }
+    (records, lines)
+}
+
+// Adds the record to the records, replacing the one of the same range if it was recorded before.
+pub fn upsert(records: &mut Vec<HistoryRecord>, record: HistoryRecord) {
+    records.retain(|other| other.start != record.start || other.end != record.end);
+    records.push(record);
+    records.sort_by_key(|record| (record.start, record.end));
+}
+
+// Returns the record of the result of the report if its range is over, so that only final results
+// are stored, and if recording isn't turned off.
+pub fn finished_record(
+    twinput: &TimeWarriorInput,
+    workgroups: &[WorkGroup],
+) -> Result<Option<HistoryRecord>, String> {
+    if twinput.get_config("aggregate.history") == Some("off") {
+        return Ok(None);
+    }
+    let end = NaiveDateTime::parse_from_str(&twinput.end, "%Y%m%dT%H%M%SZ").unwrap();
+    if end > twinput.clock.now() {
+        debug!("Not recording the history of the range ending at {}", end);
+        return Ok(None);
+    }
+    let (start, end, _) = timezone::local_range(&twinput.start, &twinput.end)?;
+    Ok(Some(HistoryRecord {
+        start,
+        end,
+        workgroups: workgroups.to_vec(),
+    }))
+}
+
+// Adds the records to the store, which is rewritten once for all of them.
+pub fn record_all(new_records: Vec<HistoryRecord>) -> Result<(), String> {
+    if new_records.is_empty() {
+        return Ok(());
+    }
+    let (mut records, invalid) = read_records();
+    for record in new_records {
+        upsert(&mut records, record);
+    }
+
+    let path = history_path();
+    let mut contents = String::new();
+    for record in records.iter() {
+        contents.push_str(&record.to_json_value().dump());
+        contents.push('\n');
+    }
+    // The invalid lines are kept, so that they can still be fixed by hand.
+    for line in invalid {
+        contents.push_str(&line);
+        contents.push('\n');
+    }
+    // The history is written to a temporary file first, so that an interrupted write doesn't lose
+    // the records already there.
+    let temp_path = path.with_extension("jsonl.tmp");
+    let result = fs::create_dir_all(path.parent().unwrap())
+        .and_then(|_| fs::write(&temp_path, contents))
+        .and_then(|_| fs::rename(&temp_path, &path));
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!(
+            "Unable to record the history at {}\nError: '{}'\n",
+            path.display(),
+            err
+        )),
+    }
+}
+
+// Records the result of the report if its range is over.
+pub fn record(twinput: &TimeWarriorInput, workgroups: &[WorkGroup]) -> Result<(), String> {
+    match finished_record(twinput, workgroups)? {
+        Some(record) => record_all(vec![record]),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_records, upsert, HistoryRecord};
+    use chrono::NaiveDate;
+
+    static RECORDS: &str = "{\"start\":\"2021-07-19\",\"end\":\"2021-07-26\",\"groups\":[{\"tags\":[\"office\",\"project\"],\"spent\":36000,\"allocated\":54000}]}
+{\"start\":\"2021-07-26\",\"end\":\"2021-08-02\",\"groups\":[{\"tags\":[\"office\",\"project\"],\"spent\":60000,\"allocated\":54000}]}
+";
+
+    #[test]
+    fn parse_records_lines() {
+        let (records, invalid) = parse_records(RECORDS);
+
+        assert_eq!(records.len(), 2);
+        assert!(invalid.is_empty());
+        assert_eq!(records[0].start, NaiveDate::from_ymd(2021, 7, 19));
+        assert_eq!(records[1].end, NaiveDate::from_ymd(2021, 8, 2));
+        assert_eq!(records[1].workgroups[0].time_spent.num_seconds(), 60000);
+        assert_eq!(records[1].workgroups[0].time_allocated.num_hours(), 15);
+    }
+
+    #[test]
+    fn parse_records_skips_invalid_lines() {
+        let s = format!("{}{{\"start\":\"2021-07-19\"}}\nnot json\n", RECORDS);
+        let (records, invalid) = parse_records(&s);
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(invalid.len(), 2);
+        assert_eq!(invalid[0].0, "{\"start\":\"2021-07-19\"}");
+        assert_eq!(invalid[0].1, "the start or the end is missing or invalid");
+        assert_eq!(invalid[1].0, "not json");
+    }
+
+    #[test]
+    fn to_json_value_round_trip() {
+        let (records, _) = parse_records(RECORDS);
+        let line = records[0].to_json_value().dump();
+        assert_eq!(line, RECORDS.lines().next().unwrap());
+    }
+
+    #[test]
+    fn upsert_replaces_same_range() {
+        let (mut records, _) = parse_records(RECORDS);
+        let record = HistoryRecord {
+            start: NaiveDate::from_ymd(2021, 7, 19),
+            end: NaiveDate::from_ymd(2021, 7, 26),
+            workgroups: Vec::new(),
+        };
+        upsert(&mut records, record);
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].start, NaiveDate::from_ymd(2021, 7, 19));
+        assert!(records[0].workgroups.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+// Commands run when a group reaches a percentage of its allocation. Hooks for every group are set
+// in the configuration, like 'aggregate.hook.100 = timew stop', and hooks for a single group in its
+// allocation file, like '"hooks": {"50": "notify-send halfway"}'.
+
+use json::{object, JsonValue};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hook {
+    pub percentage: f64,
+    pub command: String,
+}
+
+fn parse_percentage(value: &str) -> Option<f64> {
+    match value.trim_end_matches('%').parse::<f64>() {
+        Ok(val) if val > 0.0 => Some(val),
+        _ => None,
+    }
+}
+
+// Returns the hooks for every group, sorted by percentage.
+pub fn parse_from_config(config: &HashMap<String, String>) -> Result<Vec<Hook>, String> {
+    let mut hooks = Vec::new();
+    for (key, command) in config.iter() {
+        let percentage = match key.strip_prefix("aggregate.hook.") {
+            Some(val) => val,
+            None => continue,
+        };
+        match parse_percentage(percentage) {
+            Some(val) => hooks.push(Hook {
+                percentage: val,
+                command: command.clone(),
+            }),
+            None => return Err(format!("Invalid percentage in the setting '{}'\n", key)),
+        }
+    }
+    hooks.sort_by(|a, b| a.percentage.partial_cmp(&b.percentage).unwrap());
+    Ok(hooks)
+}
+
+// Returns the problems with the hooks of a group in an allocation file.
+pub fn check_json_value(jv: &JsonValue) -> Vec<String> {
+    let mut problems = Vec::new();
+    if jv.is_null() {
+        return problems;
+    }
+    if !jv.is_object() {
+        problems.push("'hooks' is not an object".to_string());
+        return problems;
+    }
+    for (percentage, command) in jv.entries() {
+        if parse_percentage(percentage).is_none() {
+            problems.push(format!("the hook '{}' is not a percentage", percentage));
+        } else if !command.is_string() {
+            problems.push(format!("the hook '{}' is not a command", percentage));
+        }
+    }
+    problems
+}
+
+// Returns the hooks of a group in an allocation file, sorted by percentage.
+pub fn parse_from_json_value(jv: &JsonValue) -> Vec<Hook> {
+    let mut hooks: Vec<Hook> = jv
+        .entries()
+        .filter_map(|(percentage, command)| {
+            Some(Hook {
+                percentage: parse_percentage(percentage)?,
+                command: command.as_str()?.to_string(),
+            })
+        })
+        .collect();
+    hooks.sort_by(|a, b| a.percentage.partial_cmp(&b.percentage).unwrap());
+    hooks
+}
+
+pub fn to_json_value(hooks: &[Hook]) -> JsonValue {
+    let mut jv = object! {};
+    for hook in hooks {
+        jv[hook.percentage.to_string()] = hook.command.clone().into();
+    }
+    jv
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_json_value, parse_from_config, parse_from_json_value, to_json_value, Hook};
+    use std::collections::HashMap;
+
+    #[test]
+    fn from_config() {
+        let mut config = HashMap::new();
+        config.insert("aggregate.hook.100".to_string(), "timew stop".to_string());
+        config.insert("aggregate.hook.50".to_string(), "echo half".to_string());
+        config.insert("aggregate.color".to_string(), "on".to_string());
+        let hooks = parse_from_config(&config).unwrap();
+        assert_eq!(
+            hooks,
+            vec![
+                Hook {
+                    percentage: 50.0,
+                    command: "echo half".to_string()
+                },
+                Hook {
+                    percentage: 100.0,
+                    command: "timew stop".to_string()
+                }
+            ]
+        );
+
+        config.insert("aggregate.hook.half".to_string(), "echo".to_string());
+        assert!(parse_from_config(&config).is_err());
+    }
+
+    #[test]
+    fn from_json_value() {
+        let jv = json::parse("{\"120\": \"timew stop\", \"75%\": \"echo\"}").unwrap();
+        assert!(check_json_value(&jv).is_empty());
+        let hooks = parse_from_json_value(&jv);
+        assert_eq!(hooks[0].percentage, 75.0);
+        assert_eq!(hooks[1].command, "timew stop");
+        assert_eq!(
+            to_json_value(&hooks).dump(),
+            "{\"75\":\"echo\",\"120\":\"timew stop\"}"
+        );
+
+        let jv = json::parse("{\"soon\": \"echo\", \"50\": 1}").unwrap();
+        assert_eq!(
+            check_json_value(&jv),
+            vec![
+                "the hook 'soon' is not a percentage",
+                "the hook '50' is not a command"
+            ]
+        );
+        assert_eq!(check_json_value(&json::parse("[]").unwrap()).len(), 1);
+    }
+}
@@ -3,17 +3,60 @@
 // ~/.timewarrior/aggregate directory. The tool is supposed to be helpful in identifying the
 // various things required to understand how to use it.
 
+use crate::report::{Report, ReportFormat};
+use crate::style::Style;
 use crate::twinput::TimeWarriorInput;
 use isatty::stdin_isatty;
 use std::env;
 use std::io::{self, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+pub mod chart;
+pub mod clock;
+pub mod duration;
+pub mod editor;
+pub mod export;
+pub mod history;
+pub mod hook;
+pub mod notify;
+pub mod plan;
+pub mod prometheus;
+pub mod report;
+pub mod serve;
+pub mod standalone;
+pub mod status;
+pub mod style;
+pub mod suggest;
+pub mod table;
 pub mod tagset;
+pub mod taskwarrior;
+pub mod team;
+#[cfg(test)]
+pub mod testutil;
+pub mod timezone;
+pub mod trend;
+pub mod twdb;
 pub mod twentry;
 pub mod twinput;
+pub mod validate;
+pub mod watch;
+pub mod week;
 pub mod workgroup;
 
+static USAGE: &str = "Usage: aggregate report [<range>] [rc.<name>=<value> ...]
+       aggregate import <export.json> [<range>] [rc.<name>=<value> ...]
+       aggregate team <name>=<export.json>... [<range>] [rc.<name>=<value> ...]
+       aggregate trend [<weeks>] [rc.<name>=<value> ...]
+       aggregate suggest-plan [<weeks>] [--median] [--force] [rc.<name>=<value> ...]
+       aggregate copy <from> <to> [--week] [--scale <factor>] [--force] [rc.<name>=<value> ...]
+       aggregate validate
+       aggregate edit [<day or week>] [--week] [rc.<name>=<value> ...]
+       aggregate watch [<range>] [--interval <seconds>] [rc.<name>=<value> ...]
+       aggregate notify [<range>] [--interval <seconds>] [rc.<name>=<value> ...]
+       aggregate serve [--bind <address>] [--port <port>] [rc.<name>=<value> ...]
+       aggregate plan-tasks <export.json> [<day or week>] [--week] [--force] [rc.<name>=<value> ...]
+";
+
 fn check_exe() {
     let current_exe = std::env::current_exe().unwrap();
     let mut expected_exe = PathBuf::new();
@@ -39,10 +82,19 @@ fn check_stdin_isatty() {
     }
 }
 
-fn main() {
-    // Check if ~/.timewarrior/aggregate directory exists.
-    env_logger::init();
+// Prints the error and exits, the errors are meant for the user of the command line.
+fn unwrap_or_exit<T>(result: Result<T, String>) -> T {
+    match result {
+        Ok(val) => val,
+        Err(error) => {
+            print!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
 
+// Reads the input passed by timewarrior when run as an extension.
+fn read_extension_input() -> TimeWarriorInput {
     check_exe();
     check_stdin_isatty();
 
@@ -51,21 +103,128 @@ fn main() {
     io::stdin()
         .read_to_string(&mut buffer)
         .expect("Unable to read standard input");
-    let twinput = match TimeWarriorInput::parse_from_str(&buffer) {
-        Ok(val) => val,
-        Err(error) => {
-            print!("{}", error);
+    let twinput = unwrap_or_exit(TimeWarriorInput::parse_from_str(&buffer));
+
+    if let Some(tz) = twinput.get_config("aggregate.timezone") {
+        unwrap_or_exit(timezone::set_timezone(tz));
+    }
+    twinput
+}
+
+fn main() {
+    // Check if ~/.timewarrior/aggregate directory exists.
+    env_logger::init();
+
+    // Timewarrior doesn't pass any arguments to extensions, so arguments mean the binary is run
+    // directly as a command.
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (twinput, record_history) = match args.first().map(|arg| arg.as_str()) {
+        None => (read_extension_input(), true),
+        Some("report") => (unwrap_or_exit(standalone::load_input(&args[1..])), true),
+        // Exports may be of someone else, so they aren't recorded in the history.
+        Some("import") if args.len() > 1 => {
+            let export_file = &args[1];
+            let twinput = unwrap_or_exit(standalone::build_input(&args[2..], |clock| {
+                export::read_export(export_file, clock)
+            }));
+            (twinput, false)
+        }
+        Some("team") => {
+            let members = unwrap_or_exit(team::load_members(&args[1..]));
+            let plan = unwrap_or_exit(workgroup::get_workgroups(&members[0].twinput));
+            let (per_member, team) = team::roll_up(&plan, &members);
+            let style = unwrap_or_exit(Style::for_report(&members[0].twinput.config));
+            unwrap_or_exit(team::print_roll_up(&members, &per_member, &team, &style));
+            return;
+        }
+        Some("trend") => {
+            let (trend, settings) = unwrap_or_exit(trend::load_trend(&args[1..]));
+            let style = unwrap_or_exit(Style::for_terminal(&settings.config));
+            trend::print_trend(&trend, &style);
+            return;
+        }
+        Some("suggest-plan") => {
+            let (path, plan) = unwrap_or_exit(suggest::suggest_plan(&args[1..]));
+            println!("{}", plan);
+            println!("Wrote the suggested plan to {}", path);
+            return;
+        }
+        Some("copy") => {
+            let (from, to) = unwrap_or_exit(plan::copy_plan(&args[1..]));
+            println!("Copied the plan at {} to {}", from, to);
+            return;
+        }
+        Some("plan-tasks") => {
+            let (path, proposal) = unwrap_or_exit(taskwarrior::plan_tasks(&args[1..]));
+            println!("{}", workgroup::to_allocation_json(&proposal.workgroups));
+            for note in proposal.notes() {
+                println!("{}", note);
+            }
+            println!("Wrote the plan proposed from the tasks to {}", path);
+            return;
+        }
+        Some("validate") => {
+            let dir = Path::new(&workgroup::config_dir()).join("allocation");
+            let (count, results) = validate::validate_all(&dir);
+            if !validate::print_validation(count, &results) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("edit") => {
+            if let Some(path) = unwrap_or_exit(editor::edit_plan(&args[1..])) {
+                println!("Saved the plan to {}", path);
+            }
+            return;
+        }
+        Some("watch") => {
+            unwrap_or_exit(watch::watch(&args[1..]));
+            return;
+        }
+        Some("notify") => {
+            unwrap_or_exit(notify::notify(&args[1..]));
+            return;
+        }
+        Some("serve") => {
+            unwrap_or_exit(serve::serve(&args[1..]));
+            return;
+        }
+        Some(_) => {
+            print!("{}{}", USAGE, standalone::RANGE_USAGE);
             std::process::exit(1);
         }
     };
 
-    let mut workgroups = match workgroup::get_workgroups(&twinput) {
-        Ok(val) => val,
-        Err(error) => {
-            print!("{}", error);
+    // Without a plan for the range, SAMPLE=1 prints a plan to start from instead of the report.
+    if env::var("SAMPLE").is_ok() {
+        let (paths, _) = unwrap_or_exit(workgroup::allocation_file_paths(&twinput));
+        if workgroup::read_first_existing(&paths).is_none() {
+            let (start, _, days) =
+                unwrap_or_exit(timezone::local_range(&twinput.start, &twinput.end));
+            print!("{}", suggest::sample(&twinput, start, days));
             std::process::exit(1);
         }
-    };
+    }
+    let mut workgroups = unwrap_or_exit(workgroup::get_workgroups(&twinput));
     workgroup::process(&twinput, &mut workgroups);
-    workgroup::print_result(&workgroups);
+    let style = unwrap_or_exit(Style::for_report(&twinput.config));
+    let mut report = Report::from_workgroups(None, &workgroups);
+    match style.format {
+        ReportFormat::Svg => unwrap_or_exit(report.add_days(&twinput, &workgroups)),
+        ReportFormat::Status | ReportFormat::Waybar => {
+            report.active = status::active_group(&twinput, &workgroups)
+        }
+        _ => {}
+    }
+    unwrap_or_exit(report::print_reports(
+        &[report],
+        &style,
+        twinput.get_config("aggregate.report.file"),
+    ));
+    // The report is already printed, so failing to record it in the history is only a warning.
+    if record_history {
+        if let Err(err) = history::record(&twinput, &workgroups) {
+            eprint!("{}", err);
+        }
+    }
 }
@@ -0,0 +1,317 @@
+// Notifies when the group of the interval still going on reaches the warning threshold of its
+// allocation (aggregate.notify.warning, 90% unless set) and when it runs out. The time left is
+// checked periodically and every threshold is notified once per range, through notify-send, the
+// terminal bell or a command set with aggregate.notify.command. The hooks of the groups are only run
+// from the same checks, for every percentage reached, including the ones already reached when
+// starting.
+
+use crate::hook::{self, Hook};
+use crate::report::ReportRow;
+use crate::standalone;
+use crate::status;
+use crate::watch;
+use crate::workgroup;
+use log::debug;
+use std::collections::HashMap;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Method {
+    None,
+    NotifySend,
+    Bell,
+    Command(String),
+}
+
+impl Method {
+    pub fn parse_from_config(config: &HashMap<String, String>) -> Result<Self, String> {
+        let command = config.get("aggregate.notify.command");
+        match (config.get("aggregate.notify.method").map(|val| val.as_str()), command) {
+            (None, None) | (Some("notify-send"), _) => Ok(Method::NotifySend),
+            (Some("none"), _) => Ok(Method::None),
+            (Some("bell"), _) => Ok(Method::Bell),
+            (None, Some(command)) | (Some("command"), Some(command)) => {
+                Ok(Method::Command(command.clone()))
+            }
+            (Some("command"), None) => {
+                Err("The notify method 'command' needs aggregate.notify.command to be set.\n".to_string())
+            }
+            (Some(other), _) => Err(format!(
+                "Unsupported value '{}' for aggregate.notify.method. Supported values are 'none', 'notify-send', 'bell' and 'command'.\n",
+                other
+            )),
+        }
+    }
+}
+
+// Returns the thresholds, as shares of the allocation, notified about.
+pub fn parse_thresholds(config: &HashMap<String, String>) -> Result<Vec<f64>, String> {
+    let warning = match config.get("aggregate.notify.warning") {
+        None => 90.0,
+        Some(value) => match value.trim_end_matches('%').parse::<f64>() {
+            Ok(val) if val > 0.0 && val < 100.0 => val,
+            _ => return Err(format!("Invalid warning threshold '{}'\n", value)),
+        },
+    };
+    Ok(vec![warning / 100.0, 1.0])
+}
+
+// Returns the environment variables describing the group to the commands run for it.
+pub fn env_vars(row: &ReportRow) -> Vec<(String, String)> {
+    vec![
+        ("AGGREGATE_GROUP".to_string(), row.name.clone()),
+        (
+            "AGGREGATE_SPENT".to_string(),
+            row.spent.num_seconds().to_string(),
+        ),
+        (
+            "AGGREGATE_ALLOCATED".to_string(),
+            row.allocated.num_seconds().to_string(),
+        ),
+        (
+            "AGGREGATE_REMAINING".to_string(),
+            row.remaining().num_seconds().to_string(),
+        ),
+        (
+            "AGGREGATE_PERCENTAGE".to_string(),
+            format!("{:.0}", row.ratio() * 100.0),
+        ),
+    ]
+}
+
+// Runs the command with the shell, with the environment variables of the group.
+pub fn run_command(command: &str, row: &ReportRow, extra: &[(&str, String)]) {
+    let mut process = Command::new("sh");
+    process.arg("-c").arg(command).envs(env_vars(row));
+    for (key, value) in extra {
+        process.env(key, value);
+    }
+    if let Err(err) = process.status() {
+        eprintln!("Unable to run the command '{}'\nError: '{}'", command, err);
+    }
+}
+
+fn message(row: &ReportRow) -> String {
+    let minutes = row.remaining().num_minutes();
+    if minutes < 0 {
+        format!("{} is {} mins over its allocation", row.name, -minutes)
+    } else {
+        format!(
+            "{} has used {:.0}% of its allocation, {} mins left",
+            row.name,
+            row.ratio() * 100.0,
+            minutes
+        )
+    }
+}
+
+fn send(method: &Method, row: &ReportRow) {
+    let message = message(row);
+    match method {
+        Method::None => {}
+        Method::NotifySend => {
+            let urgency = if row.ratio() >= 1.0 {
+                "critical"
+            } else {
+                "normal"
+            };
+            let status = Command::new("notify-send")
+                .args(["-u", urgency, "timewarrior aggregate", &message])
+                .status();
+            if let Err(err) = status {
+                eprintln!("Unable to run notify-send\nError: '{}'", err);
+            }
+        }
+        Method::Bell => println!("\x07{}", message),
+        Method::Command(command) => {
+            run_command(command, row, &[("AGGREGATE_MESSAGE", message.clone())])
+        }
+    }
+}
+
+// Tracks the highest threshold reached by every group of a range, so that every threshold is only
+// acted upon once.
+#[derive(Default)]
+pub struct Crossings {
+    reached: HashMap<(String, String), f64>,
+}
+
+impl Crossings {
+    // Returns the thresholds the share of the allocation has reached since the last check, lowest
+    // first.
+    pub fn check(&mut self, range: &str, name: &str, ratio: f64, thresholds: &[f64]) -> Vec<f64> {
+        let key = (range.to_string(), name.to_string());
+        let reached = self.reached.get(&key).cloned().unwrap_or(f64::MIN);
+        let mut crossed: Vec<f64> = thresholds
+            .iter()
+            .cloned()
+            .filter(|threshold| *threshold > reached && ratio >= *threshold)
+            .collect();
+        crossed.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        if let Some(highest) = crossed.last() {
+            self.reached.insert(key, *highest);
+        }
+        crossed
+    }
+}
+
+// Checks the group of the interval still going on periodically until interrupted.
+pub fn notify(args: &[String]) -> Result<(), String> {
+    let (interval, args) = watch::parse_args(args)?;
+    let twinput = standalone::load_input(&args)?;
+    let method = Method::parse_from_config(&twinput.config)?;
+    let thresholds = parse_thresholds(&twinput.config)?;
+    let hooks = hook::parse_from_config(&twinput.config)?;
+
+    let mut checker = Checker {
+        method,
+        thresholds,
+        hooks,
+        notified: Crossings::default(),
+        hooked: Crossings::default(),
+    };
+    loop {
+        if let Err(err) = checker.check(&args) {
+            eprint!("{}", err);
+        }
+        thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+struct Checker {
+    method: Method,
+    thresholds: Vec<f64>,
+    // The hooks of every group.
+    hooks: Vec<Hook>,
+    notified: Crossings,
+    hooked: Crossings,
+}
+
+impl Checker {
+    // Notifies about the group of the interval still going on and runs the hooks of every group.
+    fn check(&mut self, args: &[String]) -> Result<(), String> {
+        let twinput = standalone::load_input(args)?;
+        let mut workgroups = workgroup::get_workgroups(&twinput)?;
+        workgroup::process(&twinput, &mut workgroups);
+
+        for workgroup in workgroups.iter() {
+            let row = ReportRow::from_workgroup(workgroup);
+            let hooks: Vec<&Hook> = self.hooks.iter().chain(workgroup.hooks.iter()).collect();
+            let thresholds: Vec<f64> = hooks.iter().map(|hook| hook.percentage / 100.0).collect();
+            let crossed = self
+                .hooked
+                .check(&twinput.start, &row.name, row.ratio(), &thresholds);
+            for threshold in crossed {
+                for hook in hooks
+                    .iter()
+                    .filter(|hook| hook.percentage / 100.0 == threshold)
+                {
+                    debug!("Running the hook at {}% of {}", hook.percentage, row.name);
+                    let percentage = ("AGGREGATE_THRESHOLD", hook.percentage.to_string());
+                    run_command(&hook.command, &row, &[percentage]);
+                }
+            }
+        }
+
+        let active = match status::active_group(&twinput, &workgroups) {
+            Some(val) => val,
+            None => return Ok(()),
+        };
+        let row = ReportRow::from_workgroup(&workgroups[active]);
+        debug!("{} has used {:.2} of its allocation", row.name, row.ratio());
+        let crossed = self
+            .notified
+            .check(&twinput.start, &row.name, row.ratio(), &self.thresholds);
+        if !crossed.is_empty() {
+            send(&self.method, &row);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{message, parse_thresholds, Crossings, Method};
+    use crate::report::ReportRow;
+    use crate::testutil::config;
+    use chrono::Duration;
+
+    #[test]
+    fn method_parse_from_config() {
+        assert_eq!(
+            Method::parse_from_config(&config(&[])).unwrap(),
+            Method::NotifySend
+        );
+        assert_eq!(
+            Method::parse_from_config(&config(&[("aggregate.notify.command", "echo")])).unwrap(),
+            Method::Command("echo".to_string())
+        );
+        assert_eq!(
+            Method::parse_from_config(&config(&[("aggregate.notify.method", "bell")])).unwrap(),
+            Method::Bell
+        );
+        assert!(
+            Method::parse_from_config(&config(&[("aggregate.notify.method", "command")])).is_err()
+        );
+    }
+
+    #[test]
+    fn thresholds() {
+        assert_eq!(parse_thresholds(&config(&[])).unwrap(), vec![0.9, 1.0]);
+        let settings = config(&[("aggregate.notify.warning", "75%")]);
+        assert_eq!(parse_thresholds(&settings).unwrap(), vec![0.75, 1.0]);
+        assert!(parse_thresholds(&config(&[("aggregate.notify.warning", "100")])).is_err());
+    }
+
+    #[test]
+    fn crossings_once() {
+        let mut crossings = Crossings::default();
+        let thresholds = [0.9, 1.0];
+        let range = "20210729T000000Z";
+        let none: Vec<f64> = Vec::new();
+        assert_eq!(crossings.check(range, "office", 0.5, &thresholds), none);
+        assert_eq!(
+            crossings.check(range, "office", 0.92, &thresholds),
+            vec![0.9]
+        );
+        assert_eq!(crossings.check(range, "office", 0.95, &thresholds), none);
+        assert_eq!(
+            crossings.check(range, "office", 1.2, &thresholds),
+            vec![1.0]
+        );
+        assert_eq!(crossings.check(range, "office", 1.3, &thresholds), none);
+        // Every threshold passed at once is returned.
+        assert_eq!(
+            crossings.check(range, "review", 1.1, &thresholds),
+            vec![0.9, 1.0]
+        );
+        // Every range starts over.
+        assert_eq!(
+            crossings.check("20210730T000000Z", "office", 1.0, &thresholds),
+            vec![0.9, 1.0]
+        );
+    }
+
+    #[test]
+    fn message_remaining() {
+        let row = ReportRow {
+            name: "office project".to_string(),
+            spent: Duration::minutes(108),
+            allocated: Duration::hours(2),
+        };
+        assert_eq!(
+            message(&row),
+            "office project has used 90% of its allocation, 12 mins left"
+        );
+        let row = ReportRow {
+            spent: Duration::minutes(125),
+            ..row
+        };
+        assert_eq!(
+            message(&row),
+            "office project is 5 mins over its allocation"
+        );
+    }
+}
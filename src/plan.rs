@@ -0,0 +1,194 @@
+// Copies the plan of a day or a week to another one, optionally scaling the allocations, e.g. by
+// 0.8 for a short week. The files are found and written the same way get_workgroups looks for them,
+// and anything in the plan besides the allocations is copied as is.
+
+use crate::standalone::Settings;
+use crate::workgroup::{self, WeekLayout, WorkGroup};
+use chrono::{Duration, NaiveDate, Weekday};
+
+static USAGE: &str = "Usage: aggregate copy <from> <to> [--week] [--scale <factor>] [--force] [rc.<name>=<value> ...]
+A day is one of :yesterday, :day, :tomorrow or a date of the form YYYY-MM-DD. A week is one of
+:lastweek, :week, :nextweek or a date within the week along with --week.
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    Day(NaiveDate),
+    // A week, given by its first day.
+    Week(NaiveDate),
+}
+
+impl Period {
+    pub fn parse_from_str(
+        s: &str,
+        today: NaiveDate,
+        week_start: Weekday,
+        week: bool,
+    ) -> Result<Self, String> {
+        let this_week = crate::week::week_start_of(today, week_start);
+        let period = match s {
+            ":yesterday" => Period::Day(today - Duration::days(1)),
+            ":day" | ":today" => Period::Day(today),
+            ":tomorrow" => Period::Day(today + Duration::days(1)),
+            ":lastweek" => Period::Week(this_week - Duration::days(7)),
+            ":week" => Period::Week(this_week),
+            ":nextweek" => Period::Week(this_week + Duration::days(7)),
+            _ => match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+                Ok(day) if week => Period::Week(crate::week::week_start_of(day, week_start)),
+                Ok(day) => Period::Day(day),
+                Err(_) => {
+                    return Err(format!(
+                        "Unable to parse the day or week '{}'\n{}",
+                        s, USAGE
+                    ))
+                }
+            },
+        };
+        Ok(period)
+    }
+
+    // Returns the first day of the period and the day after its last one.
+    pub fn range(&self) -> (NaiveDate, NaiveDate) {
+        match self {
+            Period::Day(day) => (*day, *day + Duration::days(1)),
+            Period::Week(week_start) => (*week_start, *week_start + Duration::days(7)),
+        }
+    }
+
+    // Returns the candidate paths of the allocation file of the period, the preferred one first.
+    pub fn allocation_file_paths(&self, config_dir: &str, layout: WeekLayout) -> Vec<String> {
+        match self {
+            Period::Day(day) => vec![workgroup::day_allocation_file_path(config_dir, *day)],
+            Period::Week(week_start) => {
+                workgroup::week_allocation_file_paths(config_dir, *week_start, layout)
+            }
+        }
+    }
+}
+
+// Returns the workgroups with their allocations multiplied by the factor.
+pub fn scale(workgroups: &[WorkGroup], factor: f64) -> Vec<WorkGroup> {
+    workgroups
+        .iter()
+        .map(|workgroup| {
+            let seconds = workgroup.time_allocated.num_seconds() as f64 * factor;
+            WorkGroup::new(
+                workgroup.tagset.clone(),
+                Duration::seconds(seconds.round() as i64),
+            )
+        })
+        .collect()
+}
+
+// Copies the plan as told by the arguments and returns the paths it was copied from and to.
+pub fn copy_plan(args: &[String]) -> Result<(String, String), String> {
+    let (settings, rest) = Settings::parse_from_args(args)?;
+    let mut periods = Vec::new();
+    let mut week = false;
+    let mut force = false;
+    let mut factor = 1.0;
+    let mut rest = rest.into_iter();
+    while let Some(arg) = rest.next() {
+        match arg {
+            "--week" => week = true,
+            "--force" => force = true,
+            "--scale" => {
+                factor = match rest.next().map(|val| val.parse::<f64>()) {
+                    Some(Ok(val)) if val > 0.0 => val,
+                    _ => return Err(format!("Invalid scale\n{}", USAGE)),
+                }
+            }
+            _ => periods.push(arg),
+        }
+    }
+    let (from, to) = match periods.as_slice() {
+        [from, to] => (*from, *to),
+        _ => return Err(USAGE.to_string()),
+    };
+    let today = settings.today();
+    let from = Period::parse_from_str(from, today, settings.week_start, week)?;
+    let to = Period::parse_from_str(to, today, settings.week_start, week)?;
+    match (from, to) {
+        (Period::Day(_), Period::Day(_)) | (Period::Week(_), Period::Week(_)) => {}
+        _ => {
+            return Err(format!(
+                "Plans can only be copied from a day to a day or from a week to a week\n{}",
+                USAGE
+            ))
+        }
+    }
+
+    let layout = WeekLayout::parse_from_config(
+        settings
+            .config
+            .get("aggregate.week.layout")
+            .map(|val| val.as_str()),
+    )?;
+    let config_dir = workgroup::config_dir();
+    let from_paths = from.allocation_file_paths(&config_dir, layout);
+    let (from_path, contents) = match workgroup::read_first_existing(&from_paths) {
+        Some(val) => val,
+        None => {
+            return Err(format!(
+                "Unable to open the workgroups definition file at {}\n",
+                from_paths.join(" or ")
+            ))
+        }
+    };
+    let workgroups = scale(&workgroup::parse_allocation(&from_path, &contents)?, factor);
+
+    let to_path = workgroup::write_allocation_file(
+        &to.allocation_file_paths(&config_dir, layout),
+        &workgroup::with_allocations(&contents, &workgroups),
+        force,
+    )?;
+    Ok((from_path, to_path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{scale, Period};
+    use crate::testutil;
+    use crate::workgroup::WeekLayout;
+    use chrono::{NaiveDate, Weekday};
+
+    #[test]
+    fn parse_from_str() {
+        let today = NaiveDate::from_ymd(2021, 7, 29);
+
+        let period = Period::parse_from_str(":yesterday", today, Weekday::Mon, false).unwrap();
+        assert_eq!(period, Period::Day(NaiveDate::from_ymd(2021, 7, 28)));
+        let period = Period::parse_from_str(":nextweek", today, Weekday::Mon, false).unwrap();
+        assert_eq!(period, Period::Week(NaiveDate::from_ymd(2021, 8, 2)));
+        let period = Period::parse_from_str("2021-07-21", today, Weekday::Mon, false).unwrap();
+        assert_eq!(period, Period::Day(NaiveDate::from_ymd(2021, 7, 21)));
+        let period = Period::parse_from_str("2021-07-21", today, Weekday::Sun, true).unwrap();
+        assert_eq!(period, Period::Week(NaiveDate::from_ymd(2021, 7, 18)));
+        assert!(Period::parse_from_str("someday", today, Weekday::Mon, false).is_err());
+    }
+
+    #[test]
+    fn allocation_file_paths() {
+        let period = Period::Day(NaiveDate::from_ymd(2021, 7, 29));
+        assert_eq!(
+            period.allocation_file_paths("/aggregate", WeekLayout::IsoWeek),
+            vec!["/aggregate/allocation/2021/7/29.json"]
+        );
+        let period = Period::Week(NaiveDate::from_ymd(2021, 7, 26));
+        assert_eq!(
+            period.allocation_file_paths("/aggregate", WeekLayout::IsoWeek)[0],
+            "/aggregate/allocation/2021/W30.json"
+        );
+    }
+
+    #[test]
+    fn scale_allocations() {
+        let s = "[{\"tags\": [\"office\", \"project\"], \"allocation\": 10}, {\"tags\": [\"office\", \"review\"], \"allocation\": 2.5}]";
+        let workgroups = testutil::workgroups(s);
+
+        let scaled = scale(&workgroups, 0.8);
+        assert_eq!(scaled[0].time_allocated.num_minutes(), 8 * 60);
+        assert_eq!(scaled[1].time_allocated.num_minutes(), 120);
+        assert_eq!(scaled[1].name(), "office review");
+    }
+}
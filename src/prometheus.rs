@@ -0,0 +1,100 @@
+// Renders reports in the Prometheus text exposition format, with a gauge of the seconds spent,
+// allocated and remaining for every group. Written to a file with aggregate.report.file, the
+// metrics can be collected by the textfile collector of node_exporter.
+
+use crate::report::{Report, ReportRow};
+
+static METRICS: [(&str, &str); 3] = [
+    (
+        "aggregate_spent_seconds",
+        "Time spent on the group in the range of the report.",
+    ),
+    (
+        "aggregate_allocated_seconds",
+        "Time allocated to the group in the range of the report.",
+    ),
+    (
+        "aggregate_remaining_seconds",
+        "Time remaining of the allocation of the group, negative when it's over.",
+    ),
+];
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn value(metric: &str, row: &ReportRow) -> i64 {
+    match metric {
+        "aggregate_spent_seconds" => row.spent.num_seconds(),
+        "aggregate_allocated_seconds" => row.allocated.num_seconds(),
+        _ => row.remaining().num_seconds(),
+    }
+}
+
+// Returns the lines of the metrics of the groups, labelled with the title of their report as the
+// member when there is one.
+pub fn render(reports: &[Report]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (metric, help) in METRICS.iter() {
+        lines.push(format!("# HELP {} {}", metric, help));
+        lines.push(format!("# TYPE {} gauge", metric));
+        for report in reports {
+            let member = match &report.title {
+                Some(title) => format!(",member=\"{}\"", escape(title)),
+                None => String::new(),
+            };
+            for row in report.rows.iter() {
+                lines.push(format!(
+                    "{}{{group=\"{}\"{}}} {}",
+                    metric,
+                    escape(&row.name),
+                    member,
+                    value(metric, row)
+                ));
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+    use crate::report::Report;
+    use crate::testutil;
+    use chrono::Duration;
+
+    #[test]
+    fn render_metrics() {
+        let s = "[{\"tags\": [\"office\", \"project\"], \"allocation\": 2}, {\"tags\": [\"say \\\"hi\\\"\"], \"allocation\": 0.5}]";
+        let mut workgroups = testutil::workgroups(s);
+        workgroups[0].time_spent = Duration::minutes(90);
+        workgroups[1].time_spent = Duration::minutes(45);
+        let lines = render(&[
+            Report::from_workgroups(None, &workgroups),
+            Report::from_workgroups(Some("alice"), &workgroups[..1]),
+        ]);
+
+        assert_eq!(lines.len(), 3 * 5);
+        assert_eq!(lines[1], "# TYPE aggregate_spent_seconds gauge");
+        assert_eq!(
+            lines[2],
+            "aggregate_spent_seconds{group=\"office project\"} 5400"
+        );
+        assert_eq!(
+            lines[3],
+            "aggregate_spent_seconds{group=\"say \\\"hi\\\"\"} 2700"
+        );
+        assert_eq!(
+            lines[4],
+            "aggregate_spent_seconds{group=\"office project\",member=\"alice\"} 5400"
+        );
+        assert_eq!(
+            lines[13],
+            "aggregate_remaining_seconds{group=\"say \\\"hi\\\"\"} -900"
+        );
+    }
+}
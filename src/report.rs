@@ -0,0 +1,477 @@
+// Renders the results of the groups. The rows of a report are shared by the renderers, which write
+// the table for the terminal, a GitHub-flavoured Markdown table to paste into a wiki, a standalone
+// HTML page with bars of the time spent, an SVG chart, a line for status bars or Prometheus metrics.
+// The renderer is set with aggregate.report.format, and the report is written to the file set with
+// aggregate.report.file instead of the standard output if there is one.
+
+use crate::chart;
+use crate::prometheus;
+use crate::status;
+use crate::style::{self, Level, Style};
+use crate::table::{Cell, Table};
+use crate::timezone;
+use crate::twinput::TimeWarriorInput;
+use crate::workgroup::{self, WorkGroup};
+use chrono::{Duration, NaiveDate};
+use json::{object, JsonValue};
+use std::fs;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    Table,
+    Markdown,
+    Html,
+    Svg,
+    Status,
+    Waybar,
+    Prometheus,
+}
+
+impl ReportFormat {
+    pub fn parse_from_config(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("table") => Ok(ReportFormat::Table),
+            Some("markdown") => Ok(ReportFormat::Markdown),
+            Some("html") => Ok(ReportFormat::Html),
+            Some("svg") => Ok(ReportFormat::Svg),
+            Some("status") => Ok(ReportFormat::Status),
+            Some("waybar") => Ok(ReportFormat::Waybar),
+            Some("prometheus") => Ok(ReportFormat::Prometheus),
+            Some(other) => Err(format!(
+                "Unsupported value '{}' for aggregate.report.format. Supported values are 'table', 'markdown', 'html', 'svg', 'status', 'waybar' and 'prometheus'.\n",
+                other
+            )),
+        }
+    }
+}
+
+pub struct ReportRow {
+    pub name: String,
+    pub spent: Duration,
+    pub allocated: Duration,
+}
+
+impl ReportRow {
+    pub fn from_workgroup(workgroup: &WorkGroup) -> Self {
+        ReportRow {
+            name: workgroup.name(),
+            spent: workgroup.time_spent,
+            allocated: workgroup.time_allocated,
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        match self.allocated.checked_sub(&self.spent) {
+            Some(val) => val,
+            None => Duration::seconds(0),
+        }
+    }
+
+    pub fn ratio(&self) -> f64 {
+        style::ratio(self.spent, self.allocated)
+    }
+
+    // Returns the spent, allocated and remaining time formatted as set in the style.
+    pub fn durations(&self, style: &Style) -> [String; 3] {
+        [
+            style.duration.format(self.spent, self.allocated),
+            style.duration.format(self.allocated, self.allocated),
+            style.duration.format(self.remaining(), self.allocated),
+        ]
+    }
+
+    // Returns the cells of the row in the terminal table, with the progress bar when it's shown.
+    pub fn cells(&self, style: &Style) -> Vec<Cell> {
+        let ratio = self.ratio();
+        let [spent, allocated, remaining] = self.durations(style);
+        let mut cells = vec![
+            Cell::new(&self.name),
+            Cell::colored(&spent, ratio),
+            Cell::new(&allocated),
+            Cell::new(&remaining),
+        ];
+        if style.bars {
+            cells.push(Cell::colored(&style::progress_bar(ratio), ratio));
+        }
+        cells
+    }
+}
+
+// The time spent on every group on a day of the report.
+pub struct Day {
+    pub date: NaiveDate,
+    pub spent: Vec<Duration>,
+}
+
+pub struct Report {
+    // The title the report is shown with when there are several of them, like the members of a team.
+    pub title: Option<String>,
+    pub rows: Vec<ReportRow>,
+    pub total: ReportRow,
+    // The days of the report, only filled in for the renderers which show them.
+    pub days: Vec<Day>,
+    // The index of the row of the group the interval still going on is counted towards.
+    pub active: Option<usize>,
+}
+
+impl Report {
+    pub fn from_workgroups(title: Option<&str>, workgroups: &[WorkGroup]) -> Self {
+        let rows: Vec<ReportRow> = workgroups.iter().map(ReportRow::from_workgroup).collect();
+        let total = ReportRow {
+            name: "total".to_string(),
+            spent: rows
+                .iter()
+                .fold(Duration::seconds(0), |total, row| total + row.spent),
+            allocated: rows
+                .iter()
+                .fold(Duration::seconds(0), |total, row| total + row.allocated),
+        };
+        Report {
+            title: title.map(|val| val.to_string()),
+            rows,
+            total,
+            days: Vec::new(),
+            active: None,
+        }
+    }
+
+    // Fills in the time spent on every group each local day of the range of the input.
+    pub fn add_days(
+        &mut self,
+        twinput: &TimeWarriorInput,
+        workgroups: &[WorkGroup],
+    ) -> Result<(), String> {
+        let (start, _, days) = timezone::local_range(&twinput.start, &twinput.end)?;
+        self.days = (0..days)
+            .map(|offset| {
+                let date = start + Duration::days(offset);
+                let day_start = timezone::utc_of_local_midnight(date);
+                let day_end = timezone::utc_of_local_midnight(date + Duration::days(1));
+                let twentries: Vec<_> = twinput
+                    .twentries
+                    .iter()
+                    .filter_map(|twentry| twentry.clone().clip(day_start, day_end))
+                    .collect();
+                let mut day_workgroups: Vec<WorkGroup> = workgroups
+                    .iter()
+                    .map(|workgroup| WorkGroup::new(workgroup.tagset.clone(), Duration::zero()))
+                    .collect();
+                workgroup::process_entries(&twentries, &mut day_workgroups);
+                Day {
+                    date,
+                    spent: day_workgroups
+                        .iter()
+                        .map(|workgroup| workgroup.time_spent)
+                        .collect(),
+                }
+            })
+            .collect();
+        Ok(())
+    }
+
+    // Returns the report as JSON, with the durations in seconds.
+    pub fn to_json_value(&self) -> JsonValue {
+        let row_json = |row: &ReportRow| {
+            object! {
+                name: row.name.clone(),
+                spent: row.spent.num_seconds(),
+                allocated: row.allocated.num_seconds(),
+                remaining: row.remaining().num_seconds(),
+                percentage: (row.ratio() * 100.0).round(),
+            }
+        };
+        object! {
+            groups: self.rows.iter().map(row_json).collect::<Vec<JsonValue>>(),
+            total: row_json(&self.total),
+            active: self.active.map(|active| self.rows[active].name.clone()),
+        }
+    }
+
+    pub fn to_table(&self, style: &Style) -> Table {
+        let mut header = vec!["group", "spent", "allocated", "remaining"];
+        if style.bars {
+            header.push("progress");
+        }
+        let mut table = Table::new(&header);
+        for row in self.rows.iter().chain(Some(&self.total)) {
+            table.push(row.cells(style));
+        }
+        table
+    }
+
+    pub fn render_markdown(&self, style: &Style) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(title) = &self.title {
+            lines.push(format!("### {}", escape_markdown(title)));
+            lines.push(String::new());
+        }
+        lines.push("| group | spent | allocated | remaining |".to_string());
+        lines.push("| --- | ---: | ---: | ---: |".to_string());
+        for row in self.rows.iter() {
+            let [spent, allocated, remaining] = row.durations(style);
+            lines.push(format!(
+                "| {} | {} | {} | {} |",
+                escape_markdown(&row.name),
+                spent,
+                allocated,
+                remaining
+            ));
+        }
+        let [spent, allocated, remaining] = self.total.durations(style);
+        lines.push(format!(
+            "| **total** | **{}** | **{}** | **{}** |",
+            spent, allocated, remaining
+        ));
+        lines
+    }
+
+    fn render_html_table(&self, style: &Style) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(title) = &self.title {
+            lines.push(format!("<h2>{}</h2>", escape_html(title)));
+        }
+        lines.push("<table>".to_string());
+        lines.push(
+            "<tr><th>group</th><th>spent</th><th>allocated</th><th>remaining</th><th>progress</th></tr>"
+                .to_string(),
+        );
+        for (row, class) in self
+            .rows
+            .iter()
+            .map(|row| (row, ""))
+            .chain(Some((&self.total, " class=\"total\"")))
+        {
+            let [spent, allocated, remaining] = row.durations(style);
+            let ratio = row.ratio();
+            let level = match style::level(ratio) {
+                Level::Under => "under",
+                Level::Near => "near",
+                Level::Over => "over",
+            };
+            lines.push(format!(
+                "<tr{}><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><div class=\"bar\"><div class=\"{}\" style=\"width: {:.0}%\"></div></div></td></tr>",
+                class,
+                escape_html(&row.name),
+                spent,
+                allocated,
+                remaining,
+                level,
+                ratio.clamp(0.0, 1.0) * 100.0
+            ));
+        }
+        lines.push("</table>".to_string());
+        lines
+    }
+}
+
+static HTML_STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { padding: 0.3em 0.8em; text-align: right; border-bottom: 1px solid #ddd; }
+th:first-child, td:first-child { text-align: left; }
+tr.total td { font-weight: bold; }
+.bar { width: 12em; height: 0.8em; background: #eee; }
+.bar div { height: 100%; }
+.under { background: #4caf50; }
+.near { background: #ffb300; }
+.over { background: #e53935; }";
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Returns a standalone HTML page with the reports.
+pub fn render_html(reports: &[Report], style: &Style) -> Vec<String> {
+    let mut lines = vec![
+        "<!DOCTYPE html>".to_string(),
+        "<html>".to_string(),
+        "<head>".to_string(),
+        "<meta charset=\"utf-8\">".to_string(),
+        "<title>timewarrior aggregate</title>".to_string(),
+        format!("<style>\n{}\n</style>", HTML_STYLE),
+        "</head>".to_string(),
+        "<body>".to_string(),
+    ];
+    for report in reports {
+        lines.extend(report.render_html_table(style));
+    }
+    lines.push("</body>".to_string());
+    lines.push("</html>".to_string());
+    lines
+}
+
+// Returns the lines of the reports in the format set in the style.
+pub fn render(reports: &[Report], style: &Style) -> Vec<String> {
+    match style.format {
+        ReportFormat::Html => return render_html(reports, style),
+        ReportFormat::Svg => return chart::render_svg(reports, style.chart),
+        ReportFormat::Prometheus => return prometheus::render(reports),
+        ReportFormat::Status | ReportFormat::Waybar => {
+            return reports
+                .iter()
+                .map(|report| {
+                    let line = match style.format {
+                        ReportFormat::Waybar => status::render_waybar(report, style),
+                        _ => status::render_line(report, style),
+                    };
+                    match &report.title {
+                        Some(title) if style.format == ReportFormat::Status => {
+                            format!("{}: {}", title, line)
+                        }
+                        _ => line,
+                    }
+                })
+                .collect()
+        }
+        _ => {}
+    }
+    let mut lines = Vec::new();
+    for (index, report) in reports.iter().enumerate() {
+        if index > 0 {
+            lines.push(String::new());
+        }
+        match style.format {
+            ReportFormat::Markdown => lines.extend(report.render_markdown(style)),
+            _ => {
+                if let Some(title) = &report.title {
+                    lines.push(title.clone());
+                }
+                lines.extend(report.to_table(style).render(style, true));
+            }
+        }
+    }
+    lines
+}
+
+// Prints the reports, or writes them to the file if one is given. The file is written next to its
+// path and renamed, so that it is never read while partly written.
+pub fn print_reports(reports: &[Report], style: &Style, file: Option<&str>) -> Result<(), String> {
+    let lines = render(reports, style);
+    let path = match file {
+        Some(val) => val,
+        None => {
+            for line in lines {
+                println!("{}", line);
+            }
+            return Ok(());
+        }
+    };
+    let temp_path = format!("{}.tmp", path);
+    let contents = lines.join("\n") + "\n";
+    match fs::write(&temp_path, contents).and_then(|_| fs::rename(&temp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(format!(
+            "Unable to write the report to {}\nError: '{}'\n",
+            path, err
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render, Report, ReportFormat};
+    use crate::style::Style;
+    use crate::testutil;
+    use crate::timezone;
+    use crate::twinput::TimeWarriorInput;
+    use crate::workgroup::WorkGroup;
+    use chrono::{Duration, NaiveDate};
+
+    fn report(title: Option<&str>) -> Report {
+        let s = "[{\"tags\": [\"office\", \"project\"], \"allocation\": 2}, {\"tags\": [\"a|b\"], \"allocation\": 1}]";
+        let mut workgroups = testutil::workgroups(s);
+        workgroups[0].time_spent = Duration::minutes(90);
+        workgroups[1].time_spent = Duration::minutes(75);
+        Report::from_workgroups(title, &workgroups)
+    }
+
+    #[test]
+    fn from_workgroups() {
+        let report = report(None);
+        assert_eq!(report.rows.len(), 2);
+        assert_eq!(report.total.spent, Duration::minutes(165));
+        assert_eq!(report.total.allocated, Duration::hours(3));
+        assert_eq!(report.rows[1].remaining(), Duration::minutes(-15));
+    }
+
+    #[test]
+    fn add_days_local() {
+        let _lock = timezone::lock_timezone("Europe/Berlin");
+        let s = "temp.report.start: 20210725T220000Z\ntemp.report.end: 20210801T220000Z\n\n[\n{\"id\":1,\"start\":\"20210726T210000Z\",\"end\":\"20210726T230000Z\",\"tags\":[\"office\",\"project\"]}\n]\n";
+        let twinput = TimeWarriorInput::parse_from_str(s).unwrap();
+        let jv = json::parse("[{\"tags\": [\"office\", \"project\"], \"allocation\": 2}]").unwrap();
+        let workgroups: Vec<WorkGroup> =
+            jv.members().map(WorkGroup::parse_from_json_value).collect();
+        let mut report = Report::from_workgroups(None, &workgroups);
+        report.add_days(&twinput, &workgroups).unwrap();
+
+        // The interval is split at local midnight.
+        assert_eq!(report.days.len(), 7);
+        assert_eq!(report.days[0].date, NaiveDate::from_ymd(2021, 7, 26));
+        assert_eq!(report.days[0].spent, vec![Duration::hours(1)]);
+        assert_eq!(report.days[1].spent, vec![Duration::hours(1)]);
+    }
+
+    #[test]
+    fn to_json_value() {
+        let mut report = report(None);
+        report.active = Some(1);
+        let jv = report.to_json_value();
+        assert_eq!(jv["groups"][0]["name"], "office project");
+        assert_eq!(jv["groups"][0]["spent"], 5400);
+        assert_eq!(jv["groups"][1]["remaining"], -900);
+        assert_eq!(jv["groups"][1]["percentage"], 125.0);
+        assert_eq!(jv["total"]["allocated"], 3 * 3600);
+        assert_eq!(jv["active"], "a|b");
+    }
+
+    #[test]
+    fn render_markdown() {
+        let style = Style {
+            format: ReportFormat::Markdown,
+            ..Style::plain()
+        };
+        let lines = render(&[report(Some("alice")), report(Some("team"))], &style);
+        assert_eq!(lines[0], "### alice");
+        assert_eq!(lines[2], "| group | spent | allocated | remaining |");
+        assert_eq!(
+            lines[4],
+            "| office project | 1 hrs 30 mins | 2 hrs 0 mins | 0 hrs 30 mins |"
+        );
+        assert_eq!(
+            lines[5],
+            "| a\\|b | 1 hrs 15 mins | 1 hrs 0 mins | -0 hrs 15 mins |"
+        );
+        assert_eq!(
+            lines[6],
+            "| **total** | **2 hrs 45 mins** | **3 hrs 0 mins** | **0 hrs 15 mins** |"
+        );
+        assert_eq!(lines[8], "### team");
+    }
+
+    #[test]
+    fn render_html() {
+        let style = Style {
+            format: ReportFormat::Html,
+            ..Style::plain()
+        };
+        let lines = render(&[report(None)], &style);
+        assert_eq!(lines[0], "<!DOCTYPE html>");
+        assert_eq!(lines.last().unwrap(), "</html>");
+        assert!(lines.iter().any(|line| line.contains(
+            "<td>office project</td><td>1 hrs 30 mins</td><td>2 hrs 0 mins</td><td>0 hrs 30 mins</td><td><div class=\"bar\"><div class=\"under\" style=\"width: 75%\">"
+        )));
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("<div class=\"over\" style=\"width: 100%\">")));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("<tr class=\"total\"><td>total</td>")));
+    }
+}
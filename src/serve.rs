@@ -0,0 +1,256 @@
+// Serves the reports of the current day and week over HTTP, as JSON at /day.json and /week.json and
+// as an HTML dashboard at /. The reports are made from the timewarrior data files on every request,
+// so they are always up to date. Only localhost is served unless another address is given, and the
+// JSON can be read by pages from any origin, like widgets in the browser.
+
+use crate::report::{self, Report};
+use crate::standalone;
+use crate::status;
+use crate::style::Style;
+use crate::workgroup;
+use json::object;
+use log::debug;
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+static USAGE: &str =
+    "Usage: aggregate serve [--bind <address>] [--port <port>] [rc.<name>=<value> ...]\n";
+
+const TIMEOUT_SECS: u64 = 5;
+
+// Returns the address to listen on, 127.0.0.1:8080 unless the address or the port is given, and
+// the rest of the arguments.
+pub fn parse_args(args: &[String]) -> Result<(String, Vec<String>), String> {
+    let mut bind: IpAddr = [127, 0, 0, 1].into();
+    let mut port = 8080;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => {
+                bind = match args.next().map(|val| val.parse::<IpAddr>()) {
+                    Some(Ok(val)) => val,
+                    _ => return Err(USAGE.to_string()),
+                }
+            }
+            "--port" => {
+                port = match args.next().map(|val| val.parse::<u16>()) {
+                    Some(Ok(val)) => val,
+                    _ => return Err(USAGE.to_string()),
+                }
+            }
+            _ => rest.push(arg.clone()),
+        }
+    }
+    let address = match bind {
+        IpAddr::V4(_) => format!("{}:{}", bind, port),
+        IpAddr::V6(_) => format!("[{}]:{}", bind, port),
+    };
+    Ok((address, rest))
+}
+
+// Returns the path of a GET request, like '/day.json'.
+pub fn parse_request_path(request: &str) -> Option<&str> {
+    let line = request.lines().next()?;
+    match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        ["GET", path, ..] => Some(path.split('?').next().unwrap()),
+        _ => None,
+    }
+}
+
+// Returns the report of the range, like ':day', along with the input it is made from.
+fn load_report(range: &str, args: &[String]) -> Result<(Report, String, String), String> {
+    let mut range_args = vec![range.to_string()];
+    range_args.extend(args.iter().cloned());
+    let twinput = standalone::load_input(&range_args)?;
+    let mut workgroups = workgroup::get_workgroups(&twinput)?;
+    workgroup::process(&twinput, &mut workgroups);
+    let mut report = Report::from_workgroups(None, &workgroups);
+    report.active = status::active_group(&twinput, &workgroups);
+    Ok((report, twinput.start, twinput.end))
+}
+
+fn json_response(range: &str, args: &[String]) -> (&'static str, &'static str, String) {
+    match load_report(range, args) {
+        Ok((report, start, end)) => {
+            let mut jv = report.to_json_value();
+            jv["start"] = start.into();
+            jv["end"] = end.into();
+            ("200 OK", "application/json", jv.dump())
+        }
+        Err(err) => (
+            "500 Internal Server Error",
+            "application/json",
+            object! { error: err.trim_end() }.dump(),
+        ),
+    }
+}
+
+// Adds a paragraph for every error at the end of the body of the page.
+fn add_errors(lines: &mut Vec<String>, errors: &[String]) {
+    let position = lines
+        .iter()
+        .position(|line| line == "</body>")
+        .unwrap_or(lines.len());
+    for (offset, error) in errors.iter().enumerate() {
+        lines.insert(
+            position + offset,
+            format!("<p>{}</p>", report::escape_html(error)),
+        );
+    }
+}
+
+fn dashboard(args: &[String]) -> String {
+    let mut reports = Vec::new();
+    let mut errors = Vec::new();
+    for (range, title) in [(":day", "Today"), (":week", "This week")] {
+        match load_report(range, args) {
+            Ok((mut report, _, _)) => {
+                report.title = Some(title.to_string());
+                reports.push(report);
+            }
+            Err(err) => errors.push(format!("{}: {}", title, err.trim_end())),
+        }
+    }
+    let mut lines = report::render_html(&reports, &Style::plain());
+    add_errors(&mut lines, &errors);
+    lines.join("\n")
+}
+
+// Returns the status, content type and body of the response to the request for the path.
+pub fn respond(path: &str, args: &[String]) -> (&'static str, &'static str, String) {
+    match path {
+        "/" => ("200 OK", "text/html; charset=utf-8", dashboard(args)),
+        "/day.json" => json_response(":day", args),
+        "/week.json" => json_response(":week", args),
+        _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+    }
+}
+
+fn handle(mut stream: TcpStream, args: &[String]) {
+    // Requests are handled one at a time, so a client which doesn't send its request or doesn't
+    // read the response must not hold up the others.
+    let timeout = Some(Duration::from_secs(TIMEOUT_SECS));
+    if stream.set_read_timeout(timeout).is_err() || stream.set_write_timeout(timeout).is_err() {
+        return;
+    }
+    let mut buffer = [0; 4096];
+    let count = match stream.read(&mut buffer) {
+        Ok(val) => val,
+        Err(_) => return,
+    };
+    let request = String::from_utf8_lossy(&buffer[..count]);
+    let (status, content_type, body) = match parse_request_path(&request) {
+        Some(path) => {
+            debug!("GET {}", path);
+            respond(path, args)
+        }
+        None => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Only GET is supported\n".to_string(),
+        ),
+    };
+    // The JSON is meant for widgets too, which are pages of their own origin.
+    let cors = match content_type {
+        "application/json" => "Access-Control-Allow-Origin: *\r\n",
+        _ => "",
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        cors,
+        body
+    );
+    // The client may be gone already, which is no reason to stop serving.
+    let _ = stream.write_all(response.as_bytes());
+}
+
+// Serves the reports until interrupted.
+pub fn serve(args: &[String]) -> Result<(), String> {
+    let (address, args) = parse_args(args)?;
+    // Fail early on arguments which would keep every request from working.
+    standalone::load_input(&args)?;
+
+    let listener = match TcpListener::bind(&address) {
+        Ok(val) => val,
+        Err(err) => {
+            return Err(format!(
+                "Unable to listen on {}\nError: '{}'\n",
+                address, err
+            ))
+        }
+    };
+    println!("Serving the dashboard at http://{}/", address);
+    for stream in listener.incoming().flatten() {
+        handle(stream, &args);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{add_errors, parse_args, parse_request_path, respond};
+
+    #[test]
+    fn request_path() {
+        assert_eq!(
+            parse_request_path("GET /day.json HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            Some("/day.json")
+        );
+        assert_eq!(parse_request_path("GET /?refresh=1 HTTP/1.1"), Some("/"));
+        assert_eq!(parse_request_path("POST / HTTP/1.1"), None);
+        assert_eq!(parse_request_path(""), None);
+    }
+
+    #[test]
+    fn address() {
+        let args: Vec<String> = vec![
+            "--port", "9000", "--bind", "0.0.0.0", "--bind", "::", ":day",
+        ]
+        .into_iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        assert_eq!(parse_args(&[]).unwrap().0, "127.0.0.1:8080");
+        assert_eq!(parse_args(&args[..2]).unwrap().0, "127.0.0.1:9000");
+        assert_eq!(parse_args(&args[..4]).unwrap().0, "0.0.0.0:9000");
+        let (address, rest) = parse_args(&args).unwrap();
+        assert_eq!(address, "[::]:9000");
+        assert_eq!(rest, vec![":day"]);
+        assert!(parse_args(&args[..1]).is_err());
+        assert!(parse_args(&args[2..3]).is_err());
+        assert!(parse_args(&["--bind".to_string(), "localhost".to_string()]).is_err());
+    }
+
+    #[test]
+    fn errors_in_body() {
+        let mut lines: Vec<String> = vec!["<body>", "<table>", "</body>", "</html>"]
+            .into_iter()
+            .map(|line| line.to_string())
+            .collect();
+        add_errors(
+            &mut lines,
+            &["Today: <none>".to_string(), "Week".to_string()],
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "<body>",
+                "<table>",
+                "<p>Today: &lt;none&gt;</p>",
+                "<p>Week</p>",
+                "</body>",
+                "</html>"
+            ]
+        );
+    }
+
+    #[test]
+    fn respond_not_found() {
+        let (status, _, _) = respond("/month.json", &[]);
+        assert_eq!(status, "404 Not Found");
+    }
+}
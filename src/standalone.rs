@@ -0,0 +1,217 @@
+// Runs the aggregation without timewarrior by reading the timewarrior database or an export
+// directly. The input built here is the same as the one timewarrior passes to the extension, so
+// that the report is the same either way.
+
+use crate::clock::Clock;
+use crate::timezone;
+use crate::twdb;
+use crate::twentry::TimeWarriorEntry;
+use crate::twinput::TimeWarriorInput;
+use crate::week;
+use chrono::{Duration, NaiveDate, Weekday};
+use std::collections::HashMap;
+
+pub static RANGE_USAGE: &str =
+    "The range is one of :day, :week, :yesterday, :lastweek, <date> or <date> to <date>
+with dates of the form YYYY-MM-DD. It defaults to :day.
+";
+
+// Returns the local dates of the start and the end (exclusive) of the range given by the
+// arguments, like ':week' or '2021-07-19 to 2021-07-26'.
+pub fn parse_range(
+    args: &[&str],
+    today: NaiveDate,
+    week_start: Weekday,
+) -> Result<(NaiveDate, NaiveDate), String> {
+    let parse_date = |value: &str| match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(val) => Ok(val),
+        Err(_) => Err(format!(
+            "Unable to parse the date '{}'\n{}",
+            value, RANGE_USAGE
+        )),
+    };
+    let this_week = week::week_start_of(today, week_start);
+    let (start, end) = match args {
+        [] | [":day"] => (today, today + Duration::days(1)),
+        [":yesterday"] => (today - Duration::days(1), today),
+        [":week"] => (this_week, this_week + Duration::days(7)),
+        [":lastweek"] => (this_week - Duration::days(7), this_week),
+        [day] => {
+            let day = parse_date(day)?;
+            (day, day + Duration::days(1))
+        }
+        [start, "to", end] | [start, "-", end] => (parse_date(start)?, parse_date(end)?),
+        _ => {
+            return Err(format!(
+                "Unsupported range '{}'\n{}",
+                args.join(" "),
+                RANGE_USAGE
+            ))
+        }
+    };
+    if start >= end {
+        return Err(format!(
+            "The range {} to {} is empty\n{}",
+            start, end, RANGE_USAGE
+        ));
+    }
+    Ok((start, end))
+}
+
+// Builds the input from the timewarrior database for the range and configuration overrides
+// (rc.<name>=<value>) in the arguments.
+pub fn load_input(args: &[String]) -> Result<TimeWarriorInput, String> {
+    let db_dir = twdb::db_dir();
+    build_input(args, |clock| twdb::read_entries(&db_dir, clock))
+}
+
+// Settings which apply to every command run without timewarrior.
+pub struct Settings {
+    pub config: HashMap<String, String>,
+    pub clock: Clock,
+    pub week_start: Weekday,
+}
+
+impl Settings {
+    // Reads the timewarrior configuration with the overrides (rc.<name>=<value>) in the arguments
+    // applied and returns the settings along with the rest of the arguments.
+    pub fn parse_from_args(args: &[String]) -> Result<(Self, Vec<&str>), String> {
+        let mut config = twdb::read_config(&twdb::db_dir());
+        let mut rest = Vec::new();
+        for arg in args {
+            match arg.strip_prefix("rc.").and_then(|arg| arg.split_once('=')) {
+                Some((key, value)) => {
+                    config.insert(key.to_string(), value.to_string());
+                }
+                None => rest.push(arg.as_str()),
+            }
+        }
+
+        if let Some(tz) = config.get("aggregate.timezone") {
+            timezone::set_timezone(tz)?;
+        }
+        let clock = Clock::from_config(config.get("aggregate.now").map(|val| val.as_str()))?;
+        let week_start = week::week_start_from_config(&config)?;
+        let settings = Settings {
+            config,
+            clock,
+            week_start,
+        };
+        Ok((settings, rest))
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        timezone::local_date(&self.clock.now())
+    }
+
+    pub fn week_start_of(&self, day: NaiveDate) -> NaiveDate {
+        week::week_start_of(day, self.week_start)
+    }
+
+    // Builds the input for the range of local dates from the given intervals, which are clipped
+    // to the range. Only the intervals within the range are copied.
+    pub fn input_for_range(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        twentries: &[TimeWarriorEntry],
+    ) -> TimeWarriorInput {
+        let start = timezone::utc_of_local_midnight(start);
+        let end = timezone::utc_of_local_midnight(end);
+        let twentries = twentries
+            .iter()
+            .filter(|twentry| twentry.overlaps(start, end))
+            .filter_map(|twentry| twentry.clone().clip(start, end))
+            .collect();
+
+        TimeWarriorInput {
+            start: start.format("%Y%m%dT%H%M%SZ").to_string(),
+            end: end.format("%Y%m%dT%H%M%SZ").to_string(),
+            config: self.config.clone(),
+            clock: self.clock,
+            twentries,
+        }
+    }
+}
+
+// Builds the input for the range and configuration overrides in the arguments from the intervals
+// returned by read_entries.
+pub fn build_input<F>(args: &[String], read_entries: F) -> Result<TimeWarriorInput, String>
+where
+    F: FnOnce(&Clock) -> Result<Vec<TimeWarriorEntry>, String>,
+{
+    let (settings, range_args) = Settings::parse_from_args(args)?;
+    let (start, end) = parse_range(&range_args, settings.today(), settings.week_start)?;
+    let twentries = read_entries(&settings.clock)?;
+    Ok(settings.input_for_range(start, end, &twentries))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_range, Settings};
+    use crate::clock::Clock;
+    use crate::testutil;
+    use crate::timezone;
+    use chrono::{NaiveDate, Weekday};
+    use std::collections::HashMap;
+
+    #[test]
+    fn parse_range_hints() {
+        let today = NaiveDate::from_ymd(2021, 7, 29);
+
+        let (start, end) = parse_range(&[], today, Weekday::Mon).unwrap();
+        assert_eq!(start, today);
+        assert_eq!(end, NaiveDate::from_ymd(2021, 7, 30));
+
+        let (start, end) = parse_range(&[":week"], today, Weekday::Mon).unwrap();
+        assert_eq!(start, NaiveDate::from_ymd(2021, 7, 26));
+        assert_eq!(end, NaiveDate::from_ymd(2021, 8, 2));
+
+        let (start, end) = parse_range(&[":lastweek"], today, Weekday::Sun).unwrap();
+        assert_eq!(start, NaiveDate::from_ymd(2021, 7, 18));
+        assert_eq!(end, NaiveDate::from_ymd(2021, 7, 25));
+    }
+
+    #[test]
+    fn parse_range_dates() {
+        let today = NaiveDate::from_ymd(2021, 7, 29);
+
+        let (start, end) = parse_range(&["2021-07-21"], today, Weekday::Mon).unwrap();
+        assert_eq!(start, NaiveDate::from_ymd(2021, 7, 21));
+        assert_eq!(end, NaiveDate::from_ymd(2021, 7, 22));
+
+        let (start, end) =
+            parse_range(&["2021-07-19", "to", "2021-07-26"], today, Weekday::Mon).unwrap();
+        assert_eq!(start, NaiveDate::from_ymd(2021, 7, 19));
+        assert_eq!(end, NaiveDate::from_ymd(2021, 7, 26));
+
+        assert!(parse_range(&["2021-07-26", "to", "2021-07-19"], today, Weekday::Mon).is_err());
+        assert!(parse_range(&["someday"], today, Weekday::Mon).is_err());
+    }
+
+    #[test]
+    fn input_for_local_range() {
+        let _lock = timezone::lock_timezone("Europe/Berlin");
+        let clock = Clock::system();
+        let settings = Settings {
+            config: HashMap::new(),
+            clock,
+            week_start: Weekday::Mon,
+        };
+        let twentries = vec![testutil::entry(
+            "20210725T210000Z",
+            "20210725T230000Z",
+            "office",
+        )];
+        let twinput = settings.input_for_range(
+            NaiveDate::from_ymd(2021, 7, 26),
+            NaiveDate::from_ymd(2021, 7, 27),
+            &twentries,
+        );
+
+        assert_eq!(twinput.start, "20210725T220000Z");
+        assert_eq!(twinput.end, "20210726T220000Z");
+        // Only the part of the interval after local midnight is in the range.
+        assert_eq!(twinput.twentries[0].duration().num_minutes(), 60);
+    }
+}
@@ -0,0 +1,192 @@
+// Renders a report as a single line for status bars like i3blocks or tmux, or as the JSON of a
+// waybar custom module. The group of the interval which is still going on comes first, along with
+// the time remaining of its allocation.
+
+use crate::report::{Report, ReportRow};
+use crate::style::{self, Level, Style};
+use crate::twinput::TimeWarriorInput;
+use crate::workgroup::WorkGroup;
+use chrono::Duration;
+use json::object;
+
+// Returns the index of the group the interval which is still going on is counted towards, if any.
+pub fn active_group(twinput: &TimeWarriorInput, workgroups: &[WorkGroup]) -> Option<usize> {
+    let twentry = twinput
+        .twentries
+        .iter()
+        .rev()
+        .find(|twentry| twentry.open)?;
+    workgroups
+        .iter()
+        .position(|workgroup| workgroup.matches(twentry))
+}
+
+// Formats the duration shortly, like 1h20, 2h or 45m.
+fn format_short(duration: Duration) -> String {
+    let sign = if duration < Duration::zero() { "-" } else { "" };
+    let minutes = duration.num_minutes().abs();
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => "0".to_string(),
+        (0, minutes) => format!("{}{}m", sign, minutes),
+        (hours, 0) => format!("{}{}h", sign, hours),
+        (hours, minutes) => format!("{}{}h{:02}", sign, hours, minutes),
+    }
+}
+
+// Returns the labels of the groups, leaving out the tags every group has and shortening the rest
+// to the given number of characters.
+fn labels(report: &Report, length: usize) -> Vec<String> {
+    let tags: Vec<Vec<&str>> = report
+        .rows
+        .iter()
+        .map(|row| row.name.split(' ').collect())
+        .collect();
+    tags.iter()
+        .map(|group_tags| {
+            let distinct: Vec<&str> = group_tags
+                .iter()
+                .filter(|tag| tags.len() < 2 || !tags.iter().all(|other| other.contains(tag)))
+                .cloned()
+                .collect();
+            let group_tags = if distinct.is_empty() {
+                group_tags
+            } else {
+                &distinct
+            };
+            group_tags
+                .iter()
+                .map(|tag| tag.chars().take(length).collect::<String>())
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+fn format_group(label: &str, row: &ReportRow) -> String {
+    format!(
+        "{} {}/{}",
+        label,
+        format_short(row.spent),
+        format_short(row.allocated)
+    )
+}
+
+// Returns the time remaining of the group, or the time over its allocation.
+fn format_remaining(row: &ReportRow) -> String {
+    let remaining = row.remaining();
+    if remaining < Duration::zero() {
+        format!("{} over", format_short(-remaining))
+    } else {
+        format!("{} left", format_short(remaining))
+    }
+}
+
+// Returns the line like '● proj 1h20/2h (40m left) · revi 0/1h'.
+pub fn render_line(report: &Report, style: &Style) -> String {
+    let labels = labels(report, style.label_length);
+    let mut groups = Vec::new();
+    if let Some(active) = report.active {
+        let row = &report.rows[active];
+        groups.push(format!(
+            "● {} ({})",
+            format_group(&labels[active], row),
+            format_remaining(row)
+        ));
+    }
+    for (index, row) in report.rows.iter().enumerate() {
+        if Some(index) != report.active {
+            groups.push(format_group(&labels[index], row));
+        }
+    }
+    groups.join(" · ")
+}
+
+// Returns the JSON of a waybar custom module with the line as text, the table as tooltip and the
+// class and percentage of the active group, or of all the groups if none is active.
+pub fn render_waybar(report: &Report, style: &Style) -> String {
+    let row = match report.active {
+        Some(active) => &report.rows[active],
+        None => &report.total,
+    };
+    let class = match (report.active, style::level(row.ratio())) {
+        (None, _) => "idle",
+        (_, Level::Under) => "under",
+        (_, Level::Near) => "near",
+        (_, Level::Over) => "over",
+    };
+    let plain = Style {
+        color: false,
+        bars: false,
+        borders: false,
+        width: None,
+        ..*style
+    };
+    let jv = object! {
+        text: render_line(report, style),
+        tooltip: report.to_table(&plain).render(&plain, true).join("\n"),
+        class: class,
+        percentage: (row.ratio().min(1.0) * 100.0).round() as i64,
+    };
+    jv.dump()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{active_group, render_line, render_waybar};
+    use crate::report::Report;
+    use crate::style::Style;
+    use crate::testutil;
+    use crate::twinput::TimeWarriorInput;
+    use crate::workgroup;
+
+    fn input() -> TimeWarriorInput {
+        let s = "temp.report.start: 20210729T000000Z
+temp.report.end: 20210730T000000Z
+aggregate.now: 20210729T102000Z
+
+[
+{\"id\":2,\"start\":\"20210729T080000Z\",\"end\":\"20210729T090000Z\",\"tags\":[\"office\",\"review\"]},
+{\"id\":1,\"start\":\"20210729T090000Z\",\"tags\":[\"office\",\"project\"]}
+]";
+        TimeWarriorInput::parse_from_str(s).unwrap()
+    }
+
+    fn report(twinput: &TimeWarriorInput) -> Report {
+        let s = "[{\"tags\": [\"office\", \"project\"], \"allocation\": 2}, {\"tags\": [\"office\", \"review\"], \"allocation\": 0.75}]";
+        let mut workgroups = testutil::workgroups(s);
+        workgroup::process(twinput, &mut workgroups);
+        let mut report = Report::from_workgroups(None, &workgroups);
+        report.active = active_group(twinput, &workgroups);
+        report
+    }
+
+    #[test]
+    fn active() {
+        let twinput = input();
+        let report = report(&twinput);
+        assert_eq!(report.active, Some(0));
+    }
+
+    #[test]
+    fn line() {
+        let mut report = report(&input());
+        assert_eq!(
+            render_line(&report, &Style::plain()),
+            "● proj 1h20/2h (40m left) · revi 1h/45m"
+        );
+        report.active = None;
+        assert_eq!(
+            render_line(&report, &Style::plain()),
+            "proj 1h20/2h · revi 1h/45m"
+        );
+    }
+
+    #[test]
+    fn waybar() {
+        let report = report(&input());
+        let jv = json::parse(&render_waybar(&report, &Style::plain())).unwrap();
+        assert_eq!(jv["class"], "under");
+        assert_eq!(jv["percentage"], 67);
+        assert!(jv["tooltip"].as_str().unwrap().contains("office project"));
+    }
+}
@@ -0,0 +1,276 @@
+// Styling of the terminal table with ANSI colors and unicode progress bars. Both are used when the
+// output is a terminal and timewarrior's color setting isn't off. Since timewarrior pipes the output
+// of extensions, aggregate.color can be set to on to force them, or to off to never use them. The
+// table is fitted into the width of the terminal when it's printed there, or into
+// aggregate.table.width if it's set, and the durations in it are formatted as set with
+// aggregate.duration.format. Colors and progress bars are only used in the table for the terminal,
+// not in the other formats of the report.
+
+use crate::chart::ChartKind;
+use crate::duration::DurationFormat;
+use crate::report::ReportFormat;
+use crate::table;
+use chrono::Duration;
+use isatty::stdout_isatty;
+use std::collections::HashMap;
+
+// The share of the allocation from which a group is shown as being close to its limit.
+pub const WARNING_RATIO: f64 = 0.9;
+
+const BAR_WIDTH: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+    pub color: bool,
+    pub bars: bool,
+    // Whether the table is drawn with box-drawing borders, set with aggregate.table.borders.
+    pub borders: bool,
+    // The width the table is fitted into, if any.
+    pub width: Option<usize>,
+    pub duration: DurationFormat,
+    pub format: ReportFormat,
+    pub chart: ChartKind,
+    // The number of characters of every tag in the line for status bars.
+    pub label_length: usize,
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "on" | "yes" | "y" | "true" | "1" => Some(true),
+        "off" | "no" | "n" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+impl Style {
+    pub fn plain() -> Self {
+        Style {
+            color: false,
+            bars: false,
+            borders: false,
+            width: None,
+            duration: DurationFormat::Words,
+            format: ReportFormat::Table,
+            chart: ChartKind::Bars,
+            label_length: 4,
+        }
+    }
+
+    // Returns the style for the standard output. The table is only fitted into the width of the
+    // terminal when the output goes to it, not when it's piped or redirected.
+    pub fn for_terminal(config: &HashMap<String, String>) -> Result<Self, String> {
+        let tty = stdout_isatty();
+        let terminal_width = if tty { table::terminal_width() } else { None };
+        Style::from_config(config, tty, terminal_width)
+    }
+
+    // Returns the style for the report, which goes to aggregate.report.file when it's set and to
+    // the standard output otherwise. A file gets neither the colors nor the width of the terminal.
+    pub fn for_report(config: &HashMap<String, String>) -> Result<Self, String> {
+        match config.get("aggregate.report.file") {
+            Some(_) => Style::from_config(config, false, None),
+            None => Style::for_terminal(config),
+        }
+    }
+
+    pub fn from_config(
+        config: &HashMap<String, String>,
+        tty: bool,
+        terminal_width: Option<usize>,
+    ) -> Result<Self, String> {
+        let format = ReportFormat::parse_from_config(
+            config
+                .get("aggregate.report.format")
+                .map(|val| val.as_str()),
+        )?;
+        let enabled = match config.get("aggregate.color").map(|val| val.as_str()) {
+            None | Some("auto") => {
+                let color = config.get("color").and_then(|val| parse_bool(val));
+                tty && color != Some(false)
+            }
+            Some(value) => match parse_bool(value) {
+                Some(val) => val,
+                None => {
+                    return Err(format!(
+                        "Unsupported value '{}' for aggregate.color. Supported values are 'auto', 'on' and 'off'.\n",
+                        value
+                    ))
+                }
+            },
+        };
+        // The other formats aren't for the terminal.
+        let enabled = enabled && format == ReportFormat::Table;
+        let borders = match config.get("aggregate.table.borders").map(|val| val.as_str()) {
+            None => false,
+            Some(value) => match parse_bool(value) {
+                Some(val) => val,
+                None => {
+                    return Err(format!(
+                        "Unsupported value '{}' for aggregate.table.borders. Supported values are 'on' and 'off'.\n",
+                        value
+                    ))
+                }
+            },
+        };
+        let width = match config.get("aggregate.table.width") {
+            None => terminal_width,
+            Some(value) => match value.parse::<usize>() {
+                Ok(0) => None,
+                Ok(val) => Some(val),
+                Err(_) => return Err(format!("Invalid table width '{}'\n", value)),
+            },
+        };
+        let duration = DurationFormat::parse_from_config(
+            config
+                .get("aggregate.duration.format")
+                .map(|val| val.as_str()),
+        )?;
+        let chart =
+            ChartKind::parse_from_config(config.get("aggregate.chart").map(|val| val.as_str()))?;
+        let label_length = match config.get("aggregate.status.length") {
+            None => 4,
+            Some(value) => match value.parse::<usize>() {
+                Ok(val) if val > 0 => val,
+                _ => return Err(format!("Invalid tag length '{}'\n", value)),
+            },
+        };
+        Ok(Style {
+            color: enabled,
+            bars: enabled,
+            borders,
+            width,
+            duration,
+            format,
+            chart,
+            label_length,
+        })
+    }
+}
+
+// Returns the share of the allocation which is spent, spending without any allocation being
+// infinitely over.
+pub fn ratio(spent: Duration, allocated: Duration) -> f64 {
+    if allocated.num_seconds() <= 0 {
+        if spent.num_seconds() > 0 {
+            return f64::INFINITY;
+        }
+        return 0.0;
+    }
+    spent.num_seconds() as f64 / allocated.num_seconds() as f64
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Under,
+    Near,
+    Over,
+}
+
+// Returns whether the share of the allocation spent is under, close to or over the allocation.
+pub fn level(ratio: f64) -> Level {
+    if ratio > 1.0 {
+        Level::Over
+    } else if ratio >= WARNING_RATIO {
+        Level::Near
+    } else {
+        Level::Under
+    }
+}
+
+// Colors the text green while under the allocation, yellow when close to it and red when over.
+pub fn colorize(text: &str, ratio: f64) -> String {
+    let code = match level(ratio) {
+        Level::Over => 31,
+        Level::Near => 33,
+        Level::Under => 32,
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+// Returns a bar of the spent share of the allocation, using eighths of a block for precision.
+pub fn progress_bar(ratio: f64) -> String {
+    let partials = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = (ratio.clamp(0.0, 1.0) * (BAR_WIDTH * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if !eighths.is_multiple_of(8) {
+        bar.push(partials[eighths % 8 - 1]);
+    }
+    let padding = BAR_WIDTH - bar.chars().count();
+    bar + &" ".repeat(padding)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{colorize, progress_bar, ratio, Style};
+    use crate::testutil::config;
+    use chrono::Duration;
+
+    #[test]
+    fn for_report_file() {
+        // A report written to a file doesn't depend on the terminal.
+        let style =
+            Style::for_report(&config(&[("aggregate.report.file", "/tmp/report")])).unwrap();
+        assert!(!style.color);
+        assert_eq!(style.width, None);
+        let settings = config(&[
+            ("aggregate.report.file", "/tmp/report"),
+            ("aggregate.table.width", "60"),
+        ]);
+        assert_eq!(Style::for_report(&settings).unwrap().width, Some(60));
+    }
+
+    #[test]
+    fn from_config() {
+        assert!(Style::from_config(&config(&[]), true, None).unwrap().color);
+        assert!(!Style::from_config(&config(&[]), false, None).unwrap().color);
+        assert_eq!(
+            Style::from_config(&config(&[("color", "off")]), true, None).unwrap(),
+            Style::plain()
+        );
+        assert!(
+            Style::from_config(&config(&[("aggregate.color", "on")]), false, None)
+                .unwrap()
+                .bars
+        );
+        assert!(Style::from_config(&config(&[("aggregate.color", "always")]), true, None).is_err());
+    }
+
+    #[test]
+    fn from_config_table() {
+        let style = Style::from_config(&config(&[]), true, Some(80)).unwrap();
+        assert_eq!(style.width, Some(80));
+        assert!(!style.borders);
+        let settings = config(&[
+            ("aggregate.table.borders", "on"),
+            ("aggregate.table.width", "0"),
+        ]);
+        let style = Style::from_config(&settings, true, Some(80)).unwrap();
+        assert_eq!(style.width, None);
+        assert!(style.borders);
+    }
+
+    #[test]
+    fn ratio_of_allocation() {
+        assert_eq!(ratio(Duration::minutes(30), Duration::hours(1)), 0.5);
+        assert_eq!(ratio(Duration::minutes(0), Duration::hours(0)), 0.0);
+        assert!(ratio(Duration::minutes(1), Duration::hours(0)).is_infinite());
+    }
+
+    #[test]
+    fn colorize_by_ratio() {
+        assert_eq!(colorize("1 hrs", 0.5), "\x1b[32m1 hrs\x1b[0m");
+        assert_eq!(colorize("1 hrs", 0.95), "\x1b[33m1 hrs\x1b[0m");
+        assert_eq!(colorize("1 hrs", 1.5), "\x1b[31m1 hrs\x1b[0m");
+    }
+
+    #[test]
+    fn progress_bar_eighths() {
+        assert_eq!(progress_bar(0.0), " ".repeat(20));
+        assert_eq!(progress_bar(1.0), "█".repeat(20));
+        assert_eq!(progress_bar(2.0), "█".repeat(20));
+        assert_eq!(
+            progress_bar(0.5125),
+            format!("{}▎{}", "█".repeat(10), " ".repeat(9))
+        );
+    }
+}
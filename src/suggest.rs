@@ -0,0 +1,364 @@
+// Suggests a plan from the time actually spent in the last weeks. The time is grouped by the tag
+// sets already used in the allocation files and every group is allocated the average (or the
+// median) of the time spent on it in those weeks.
+
+use crate::standalone::Settings;
+use crate::tagset::TagSet;
+use crate::twdb;
+use crate::twentry::TimeWarriorEntry;
+use crate::twinput::TimeWarriorInput;
+use crate::week;
+use crate::workgroup::{self, WeekLayout, WorkGroup};
+use chrono::{Duration, NaiveDate, Weekday};
+use log::debug;
+use std::cmp::Reverse;
+use std::fs;
+use std::path::Path;
+
+// The sample given to first time users who don't have any allocation files yet.
+static SAMPLE: &str = "
+[
+    {
+        \"tags\": [
+            \"office\",
+            \"project\"
+        ],
+        \"allocation\": 3
+    },
+    {
+        \"tags\": [
+            \"office\",
+            \"maintenance\"
+        ],
+        \"allocation\": 3
+    },
+    {
+        \"tags\": [
+            \"office\",
+            \"review\"
+        ],
+        \"allocation\": 1
+    }
+]
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Statistic {
+    Average,
+    Median,
+}
+
+impl Statistic {
+    fn of(&self, values: &mut [f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0;
+        }
+        match self {
+            Statistic::Average => values.iter().sum::<f64>() / values.len() as f64,
+            Statistic::Median => {
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let middle = values.len() / 2;
+                if values.len().is_multiple_of(2) {
+                    (values[middle - 1] + values[middle]) / 2.0
+                } else {
+                    values[middle]
+                }
+            }
+        }
+    }
+}
+
+// Returns the distinct tag sets of the groups in the given allocation files, in the order they are
+// first seen. Files and groups which can't be parsed are skipped.
+pub fn collect_tagsets(paths: &[impl AsRef<Path>]) -> Vec<TagSet> {
+    let mut tagsets: Vec<TagSet> = Vec::new();
+    for path in paths {
+        let parsed_json = match fs::read_to_string(path).map(|contents| json::parse(&contents)) {
+            Ok(Ok(val)) => val,
+            _ => {
+                debug!("Skipping {}", path.as_ref().display());
+                continue;
+            }
+        };
+        for jv in parsed_json.members() {
+            if !WorkGroup::check_json_value(jv).is_empty() {
+                continue;
+            }
+            let tagset = TagSet::parse_from_json_value(jv);
+            if !tagsets.iter().any(|other| other.tags == tagset.tags) {
+                tagsets.push(tagset);
+            }
+        }
+    }
+    tagsets
+}
+
+// Suggests the workgroups for a range of the given number of days from the time spent on the tag
+// sets in each of the weeks of the inputs. Like in a report, an interval counts towards the first
+// group it matches, so the tag sets with the most tags come first and a broad tag set like 'office'
+// only gets the time no more specific one like 'office project' has. Allocations are rounded to a
+// quarter of an hour and groups nothing was spent on are left out.
+pub fn suggest(
+    tagsets: &[TagSet],
+    week_inputs: &[TimeWarriorInput],
+    statistic: Statistic,
+    days: i64,
+) -> Vec<WorkGroup> {
+    let mut tagsets = tagsets.to_vec();
+    tagsets.sort_by_key(|tagset| Reverse(tagset.tags.len()));
+    let mut hours_per_week = vec![Vec::new(); tagsets.len()];
+    for twinput in week_inputs {
+        let mut workgroups: Vec<WorkGroup> = tagsets
+            .iter()
+            .map(|tagset| WorkGroup::new(tagset.clone(), Duration::seconds(0)))
+            .collect();
+        workgroup::process(twinput, &mut workgroups);
+        for (hours, workgroup) in hours_per_week.iter_mut().zip(workgroups) {
+            hours.push(workgroup.time_spent.num_seconds() as f64 / 3600.0);
+        }
+    }
+
+    let mut suggestion = Vec::new();
+    for (tagset, hours) in tagsets.iter().zip(hours_per_week.iter_mut()) {
+        let allocation = statistic.of(hours) * days as f64 / 7.0;
+        let quarters = (allocation * 4.0).round() as i64;
+        if quarters > 0 {
+            suggestion.push(WorkGroup::new(
+                tagset.clone(),
+                Duration::minutes(quarters * 15),
+            ));
+        }
+    }
+    suggestion
+}
+
+// Returns the inputs of the weeks starting on the given days.
+fn week_inputs(
+    settings: &Settings,
+    weeks: &[NaiveDate],
+    twentries: &[TimeWarriorEntry],
+) -> Vec<TimeWarriorInput> {
+    weeks
+        .iter()
+        .map(|week| settings.input_for_range(*week, *week + Duration::days(7), twentries))
+        .collect()
+}
+
+// Returns a sample plan for the range of the given number of days starting on the given day, made
+// from the last four weeks before it. The static sample is returned when there is nothing to
+// suggest from.
+pub fn sample(twinput: &TimeWarriorInput, start: NaiveDate, days: i64) -> String {
+    let config_dir = workgroup::config_dir();
+    let tagsets = collect_tagsets(&workgroup::allocation_files(
+        &Path::new(&config_dir).join("allocation"),
+    ));
+    let twentries = twdb::read_entries(&twdb::db_dir(), &twinput.clock).unwrap_or_default();
+    if tagsets.is_empty() || twentries.is_empty() {
+        return SAMPLE.to_string();
+    }
+
+    let weeks: Vec<NaiveDate> = (1..=4)
+        .rev()
+        .map(|n| start - Duration::days(7 * n))
+        .collect();
+    let settings = Settings {
+        config: twinput.config.clone(),
+        clock: twinput.clock,
+        week_start: week::week_start_from_config(&twinput.config).unwrap_or(Weekday::Mon),
+    };
+    let suggestion = suggest(
+        &tagsets,
+        &week_inputs(&settings, &weeks, &twentries),
+        Statistic::Average,
+        days,
+    );
+    if suggestion.is_empty() {
+        return SAMPLE.to_string();
+    }
+    workgroup::to_allocation_json(&suggestion)
+}
+
+// Writes a plan for the next week suggested from the last weeks, four unless given otherwise.
+pub fn suggest_plan(args: &[String]) -> Result<(String, String), String> {
+    let (settings, rest) = Settings::parse_from_args(args)?;
+    let mut statistic = Statistic::Average;
+    let mut force = false;
+    let mut count = 4;
+    for arg in rest {
+        match arg {
+            "--median" => statistic = Statistic::Median,
+            "--force" => force = true,
+            _ => match arg.parse::<i64>() {
+                Ok(val) if val > 0 => count = val,
+                _ => {
+                    return Err(
+                        "Usage: aggregate suggest-plan [<weeks>] [--median] [--force] [rc.<name>=<value> ...]\n"
+                            .to_string(),
+                    )
+                }
+            },
+        }
+    }
+
+    let config_dir = workgroup::config_dir();
+    let tagsets = collect_tagsets(&workgroup::allocation_files(
+        &Path::new(&config_dir).join("allocation"),
+    ));
+    if tagsets.is_empty() {
+        return Err(format!(
+            "No task groups found in the allocation files at {}/allocation\n",
+            config_dir
+        ));
+    }
+
+    let this_week = settings.week_start_of(settings.today());
+    let weeks: Vec<NaiveDate> = (1..=count)
+        .rev()
+        .map(|n| this_week - Duration::days(7 * n))
+        .collect();
+    let twentries = twdb::read_entries(&twdb::db_dir(), &settings.clock)?;
+    let suggestion = suggest(
+        &tagsets,
+        &week_inputs(&settings, &weeks, &twentries),
+        statistic,
+        7,
+    );
+    if suggestion.is_empty() {
+        return Err(format!(
+            "No time was spent on the task groups in the last {} weeks\n",
+            count
+        ));
+    }
+
+    let layout = WeekLayout::parse_from_config(
+        settings
+            .config
+            .get("aggregate.week.layout")
+            .map(|val| val.as_str()),
+    )?;
+    let next_week = this_week + Duration::days(7);
+    let plan = workgroup::to_allocation_json(&suggestion);
+    let path = workgroup::write_allocation_file(
+        &workgroup::week_allocation_file_paths(&config_dir, next_week, layout),
+        &plan,
+        force,
+    )?;
+    Ok((path, plan))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{suggest, Statistic};
+    use crate::tagset::TagSet;
+    use crate::testutil::{entry, input_for_range};
+    use crate::twentry::TimeWarriorEntry;
+    use crate::twinput::TimeWarriorInput;
+    use crate::workgroup;
+    use chrono::{Duration, NaiveDate};
+
+    fn week_inputs(weeks: &[NaiveDate], twentries: &[TimeWarriorEntry]) -> Vec<TimeWarriorInput> {
+        weeks
+            .iter()
+            .map(|week| input_for_range(*week, *week + Duration::days(7), twentries))
+            .collect()
+    }
+
+    #[test]
+    fn statistic_of() {
+        assert_eq!(Statistic::Average.of(&mut [1.0, 2.0, 6.0]), 3.0);
+        assert_eq!(Statistic::Median.of(&mut [6.0, 1.0, 2.0]), 2.0);
+        assert_eq!(Statistic::Median.of(&mut [6.0, 1.0, 2.0, 3.0]), 2.5);
+        assert_eq!(Statistic::Median.of(&mut []), 0.0);
+    }
+
+    #[test]
+    fn suggest_from_weeks() {
+        let tagsets = vec![
+            TagSet::parse_from_data_str("office project"),
+            TagSet::parse_from_data_str("office review"),
+            TagSet::parse_from_data_str("personal learning"),
+        ];
+        let twentries = vec![
+            entry(
+                "20210713T080000Z",
+                "20210713T180000Z",
+                "office project \"task 1\"",
+            ),
+            entry("20210720T080000Z", "20210720T100000Z", "office project"),
+            entry("20210721T080000Z", "20210721T090000Z", "office review"),
+        ];
+        let weeks = vec![
+            NaiveDate::from_ymd(2021, 7, 12),
+            NaiveDate::from_ymd(2021, 7, 19),
+        ];
+
+        let suggestion = suggest(
+            &tagsets,
+            &week_inputs(&weeks, &twentries),
+            Statistic::Average,
+            7,
+        );
+        assert_eq!(suggestion.len(), 2);
+        assert_eq!(suggestion[0].name(), "office project");
+        assert_eq!(suggestion[0].time_allocated.num_minutes(), 6 * 60);
+        assert_eq!(suggestion[1].name(), "office review");
+        assert_eq!(suggestion[1].time_allocated.num_minutes(), 30);
+
+        // A day gets a seventh of the week rounded to a quarter of an hour.
+        let suggestion = suggest(
+            &tagsets,
+            &week_inputs(&weeks, &twentries),
+            Statistic::Average,
+            1,
+        );
+        assert_eq!(suggestion.len(), 1);
+        assert_eq!(suggestion[0].time_allocated.num_minutes(), 45);
+    }
+
+    #[test]
+    fn suggest_to_allocation_json() {
+        let tagsets = vec![TagSet::parse_from_data_str("office project")];
+        let twentries = vec![entry(
+            "20210720T080000Z",
+            "20210720T103000Z",
+            "office project",
+        )];
+        let weeks = vec![NaiveDate::from_ymd(2021, 7, 19)];
+        let suggestion = suggest(
+            &tagsets,
+            &week_inputs(&weeks, &twentries),
+            Statistic::Median,
+            7,
+        );
+
+        assert_eq!(
+            workgroup::to_allocation_json(&suggestion),
+            "[\n    {\n        \"tags\": [\n            \"office\",\n            \"project\"\n        ],\n        \"allocation\": 2.5\n    }\n]"
+        );
+    }
+
+    #[test]
+    fn suggest_specific_tagsets_first() {
+        let tagsets = vec![
+            TagSet::parse_from_data_str("office"),
+            TagSet::parse_from_data_str("office project"),
+        ];
+        let twentries = vec![
+            entry("20210720T080000Z", "20210720T100000Z", "office project"),
+            entry("20210720T100000Z", "20210720T110000Z", "office meeting"),
+        ];
+        let weeks = vec![NaiveDate::from_ymd(2021, 7, 19)];
+
+        // The project isn't counted towards office, which comes first in the allocation files.
+        let suggestion = suggest(
+            &tagsets,
+            &week_inputs(&weeks, &twentries),
+            Statistic::Average,
+            7,
+        );
+        assert_eq!(suggestion[0].name(), "office project");
+        assert_eq!(suggestion[0].time_allocated.num_minutes(), 120);
+        assert_eq!(suggestion[1].name(), "office");
+        assert_eq!(suggestion[1].time_allocated.num_minutes(), 60);
+    }
+}
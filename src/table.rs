@@ -0,0 +1,227 @@
+// Lays out tables for the terminal. Columns are as wide as their content, the first column is
+// truncated with an ellipsis when the table doesn't fit the width of the terminal, and the table is
+// optionally drawn with box-drawing borders.
+
+use crate::style::{self, Style};
+use std::env;
+use std::fs::File;
+use std::process::Command;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// The first column isn't truncated to less than this, even if the table doesn't fit then.
+const MIN_WIDTH: usize = 8;
+
+pub struct Cell {
+    pub text: String,
+    // The share of the allocation spent, which the cell is colored by when colors are used.
+    pub ratio: Option<f64>,
+}
+
+impl Cell {
+    pub fn new(text: &str) -> Self {
+        Cell {
+            text: text.to_string(),
+            ratio: None,
+        }
+    }
+
+    pub fn colored(text: &str, ratio: f64) -> Self {
+        Cell {
+            text: text.to_string(),
+            ratio: Some(ratio),
+        }
+    }
+}
+
+pub struct Table {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+// Returns the width of the terminal from the COLUMNS environment variable or from stty, which is
+// run on /dev/tty since the standard input isn't the terminal.
+pub fn terminal_width() -> Option<usize> {
+    if let Some(val) = env::var("COLUMNS").ok().and_then(|val| val.parse().ok()) {
+        return Some(val);
+    }
+    let tty = File::open("/dev/tty").ok()?;
+    let output = Command::new("stty").arg("size").stdin(tty).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let size = String::from_utf8_lossy(&output.stdout).to_string();
+    size.split_whitespace().nth(1)?.parse().ok()
+}
+
+// Truncates the text to the width, ending it with an ellipsis when anything is cut off.
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if truncated_width + char_width + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        truncated_width += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(text.width());
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+impl Table {
+    pub fn new(header: &[&str]) -> Self {
+        Table {
+            header: header.iter().map(|title| title.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Cell>) {
+        self.rows.push(row);
+    }
+
+    // Returns the width of every column, fitting the table into the width of the style by
+    // truncating the first column.
+    fn widths(&self, style: &Style) -> Vec<usize> {
+        let mut widths: Vec<usize> = self.header.iter().map(|title| title.width()).collect();
+        for row in self.rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.text.width());
+            }
+        }
+        if let Some(max_width) = style.width {
+            // Every column is separated by 3 characters, with 2 more at the edges.
+            let separators = 3 * widths.len() + 1;
+            let total = widths.iter().sum::<usize>() + separators;
+            if total > max_width {
+                let minimum = widths[0].min(MIN_WIDTH);
+                widths[0] = widths[0].saturating_sub(total - max_width).max(minimum);
+            }
+        }
+        widths
+    }
+
+    fn format_line(cells: &[String], style: &Style) -> String {
+        if style.borders {
+            format!("│ {} │", cells.join(" │ "))
+        } else {
+            format!("| {}", cells.join(" | ")).trim_end().to_string()
+        }
+    }
+
+    fn border(widths: &[usize], left: &str, middle: &str, right: &str) -> String {
+        let lines: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+        format!("{}{}{}", left, lines.join(middle), right)
+    }
+
+    // Returns the lines of the table, with a separator before the last row when it's a total.
+    pub fn render(&self, style: &Style, total: bool) -> Vec<String> {
+        let widths = self.widths(style);
+        let header: Vec<String> = self
+            .header
+            .iter()
+            .zip(widths.iter())
+            .map(|(title, width)| pad(&truncate(title, *width), *width))
+            .collect();
+
+        let mut lines = Vec::new();
+        if style.borders {
+            lines.push(Table::border(&widths, "┌", "┬", "┐"));
+        }
+        lines.push(Table::format_line(&header, style));
+        if style.borders {
+            lines.push(Table::border(&widths, "├", "┼", "┤"));
+        }
+        for (index, row) in self.rows.iter().enumerate() {
+            if style.borders && total && index + 1 == self.rows.len() && index > 0 {
+                lines.push(Table::border(&widths, "├", "┼", "┤"));
+            }
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| {
+                    let text = pad(&truncate(&cell.text, *width), *width);
+                    match cell.ratio {
+                        Some(ratio) if style.color => style::colorize(&text, ratio),
+                        _ => text,
+                    }
+                })
+                .collect();
+            lines.push(Table::format_line(&cells, style));
+        }
+        if style.borders {
+            lines.push(Table::border(&widths, "└", "┴", "┘"));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{truncate, Cell, Table};
+    use crate::style::Style;
+
+    fn table() -> Table {
+        let mut table = Table::new(&["group", "spent"]);
+        table.push(vec![
+            Cell::new("learning personal Rust talks"),
+            Cell::new("1 hrs 0 mins"),
+        ]);
+        table.push(vec![Cell::new("読書"), Cell::new("0 hrs 30 mins")]);
+        table
+    }
+
+    #[test]
+    fn truncate_unicode() {
+        assert_eq!(truncate("office", 10), "office");
+        assert_eq!(truncate("learning personal", 10), "learning …");
+        assert_eq!(truncate("読書する", 6), "読書…");
+    }
+
+    #[test]
+    fn render_widths_from_content() {
+        let lines = table().render(&Style::plain(), false);
+        assert_eq!(lines[0], format!("| group{} | spent", " ".repeat(23)));
+        assert_eq!(lines[1], "| learning personal Rust talks | 1 hrs 0 mins");
+        assert_eq!(
+            lines[2],
+            format!("| 読書{} | 0 hrs 30 mins", " ".repeat(24))
+        );
+    }
+
+    #[test]
+    fn render_fits_width() {
+        let style = Style {
+            width: Some(30),
+            ..Style::plain()
+        };
+        let lines = table().render(&style, false);
+        assert_eq!(lines[1], "| learning … | 1 hrs 0 mins");
+        assert_eq!(lines[2], "| 読書       | 0 hrs 30 mins");
+    }
+
+    #[test]
+    fn render_borders() {
+        let style = Style {
+            borders: true,
+            ..Style::plain()
+        };
+        let lines = table().render(&style, true);
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], format!("┌{}┬{}┐", "─".repeat(30), "─".repeat(15)));
+        assert_eq!(lines[4], Table::border(&[28, 13], "├", "┼", "┤"));
+        assert_eq!(
+            lines[5],
+            format!("│ 読書{} │ 0 hrs 30 mins │", " ".repeat(24))
+        );
+    }
+}
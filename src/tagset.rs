@@ -1,7 +1,7 @@
 use json::JsonValue;
 use std::collections::HashSet;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TagSet {
     pub tags: HashSet<String>,
 }
@@ -15,6 +15,57 @@ impl TagSet {
         TagSet { tags }
     }
 
+    // Parses the tags of an interval as stored in the timewarrior data files, like
+    // 'office "Rust talks" # "annotation"'. Tags with spaces are quoted and the annotation
+    // following the '#' is not a tag.
+    pub fn parse_from_data_str(s: &str) -> Self {
+        let mut tags = HashSet::new();
+        let mut chars = s.chars().peekable();
+        loop {
+            while chars.peek() == Some(&' ') {
+                chars.next();
+            }
+            let mut tag = String::new();
+            match chars.peek() {
+                None => break,
+                Some('"') => {
+                    chars.next();
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '\\' => {
+                                if let Some(escaped) = chars.next() {
+                                    tag.push(escaped);
+                                }
+                            }
+                            '"' => break,
+                            _ => tag.push(c),
+                        }
+                    }
+                }
+                Some(_) => {
+                    while let Some(&c) = chars.peek() {
+                        if c == ' ' {
+                            break;
+                        }
+                        tag.push(c);
+                        chars.next();
+                    }
+                    if tag == "#" {
+                        break;
+                    }
+                }
+            }
+            tags.insert(tag);
+        }
+        TagSet { tags }
+    }
+
+    pub fn sorted_tags(&self) -> Vec<String> {
+        let mut tags_sorted: Vec<String> = self.tags.iter().cloned().collect();
+        tags_sorted.sort();
+        tags_sorted
+    }
+
     pub fn has_all_tags_of(&self, other: &TagSet) -> bool {
         let intersection: HashSet<_> = self.tags.intersection(&(other.tags)).collect();
         intersection.len() == self.tags.len()
@@ -28,7 +79,7 @@ mod test {
     #[test]
     fn parse_from_json_value() {
         let s = "{ \"tags\": [ \"office\", \"project\" ] }";
-        let jv = json::parse(&s).unwrap();
+        let jv = json::parse(s).unwrap();
         let tagset = TagSet::parse_from_json_value(&jv);
 
         assert_eq!(tagset.tags.len(), 2);
@@ -39,18 +90,31 @@ mod test {
     #[test]
     fn has_all_tags_of() {
         let s = "{ \"tags\": [ \"office\", \"project\" ] }";
-        let jv = json::parse(&s).unwrap();
+        let jv = json::parse(s).unwrap();
         let tagset1 = TagSet::parse_from_json_value(&jv);
 
         let s = "{ \"tags\": [ \"office\", \"project\", \"foo\" ] }";
-        let jv = json::parse(&s).unwrap();
+        let jv = json::parse(s).unwrap();
         let tagset2 = TagSet::parse_from_json_value(&jv);
 
         let s = "{ \"tags\": [ \"office\", \"maintenance\" ] }";
-        let jv = json::parse(&s).unwrap();
+        let jv = json::parse(s).unwrap();
         let tagset3 = TagSet::parse_from_json_value(&jv);
 
         assert!(tagset1.has_all_tags_of(&tagset2));
         assert!(!tagset1.has_all_tags_of(&tagset3));
     }
+
+    #[test]
+    fn parse_from_data_str() {
+        let tagset = TagSet::parse_from_data_str(
+            "\"Rust talks\" learning \"say \\\"hi\\\"\" personal # \"watched # talk\"",
+        );
+
+        assert_eq!(tagset.tags.len(), 4);
+        assert!(tagset.tags.contains("Rust talks"));
+        assert!(tagset.tags.contains("learning"));
+        assert!(tagset.tags.contains("say \"hi\""));
+        assert!(tagset.tags.contains("personal"));
+    }
 }
@@ -0,0 +1,386 @@
+// Proposes a plan from the tasks of taskwarrior. The pending tasks due or scheduled in a day or a
+// week are read from the output of 'task export' and grouped by their project and tags, the way
+// taskwarrior's timewarrior hook tags the intervals of the tasks. Every group is allocated the sum
+// of the estimates of its tasks, taken from the 'estimate' or 'duration' UDA unless the UDA is set
+// with aggregate.taskwarrior.estimate.
+
+use crate::plan::Period;
+use crate::standalone::Settings;
+use crate::tagset::TagSet;
+use crate::timezone;
+use crate::workgroup::{self, WeekLayout, WorkGroup};
+use chrono::{Duration, NaiveDateTime};
+use json::JsonValue;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+
+static USAGE: &str = "Usage: aggregate plan-tasks <export.json> [<day or week>] [--week] [--force] [rc.<name>=<value> ...]
+The export is the output of 'task export', '-' being the standard input. A day is one of :yesterday,
+:day, :tomorrow or a date of the form YYYY-MM-DD. A week is one of :lastweek, :week, :nextweek or a
+date within the week along with --week. It defaults to :week.
+";
+
+#[derive(Clone, Debug)]
+pub struct Task {
+    pub tagset: TagSet,
+    pub estimate: Option<Duration>,
+    // The due and scheduled times of the task, in UTC.
+    pub dates: Vec<NaiveDateTime>,
+}
+
+// Returns the sum of the numbers followed by units in the string, like '1h30min', with the seconds
+// of a unit given by seconds_of.
+fn sum_of_parts(s: &str, seconds_of: impl Fn(&str) -> Option<f64>) -> Option<f64> {
+    let is_number = |c: char| c.is_ascii_digit() || c == '.';
+    let mut total = 0.0;
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        let number_length = rest.find(|c| !is_number(c)).unwrap_or(rest.len());
+        let unit_length = rest[number_length..]
+            .find(is_number)
+            .unwrap_or(rest.len() - number_length);
+        let number = rest[..number_length].parse::<f64>().ok()?;
+        let unit = rest[number_length..number_length + unit_length].trim();
+        total += number * seconds_of(unit)?;
+        rest = &rest[number_length + unit_length..];
+    }
+    Some(total)
+}
+
+// Parses an estimate, either a number of hours, an ISO 8601 duration like 'PT1H30M' as exported
+// for duration UDAs, or a duration like '90min' or '1h30min'.
+pub fn parse_estimate(jv: &JsonValue) -> Option<Duration> {
+    let seconds = if let Some(hours) = jv.as_f64() {
+        hours * 3600.0
+    } else {
+        let s = jv.as_str()?.trim();
+        if s.is_empty() {
+            return None;
+        }
+        match s.strip_prefix('P') {
+            Some(iso) => {
+                let (date, time) = iso.split_once('T').unwrap_or((iso, ""));
+                let date = sum_of_parts(date, |unit| match unit {
+                    "Y" => Some(365.0 * 86400.0),
+                    "M" => Some(30.0 * 86400.0),
+                    "W" => Some(7.0 * 86400.0),
+                    "D" => Some(86400.0),
+                    _ => None,
+                })?;
+                let time = sum_of_parts(time, |unit| match unit {
+                    "H" => Some(3600.0),
+                    "M" => Some(60.0),
+                    "S" => Some(1.0),
+                    _ => None,
+                })?;
+                date + time
+            }
+            None => sum_of_parts(s, |unit| match unit {
+                "" | "h" | "hr" | "hrs" | "hour" | "hours" => Some(3600.0),
+                "m" | "min" | "mins" | "minute" | "minutes" => Some(60.0),
+                "s" | "sec" | "secs" | "second" | "seconds" => Some(1.0),
+                "d" | "day" | "days" => Some(86400.0),
+                _ => None,
+            })?,
+        }
+    };
+    if seconds < 0.0 {
+        return None;
+    }
+    Some(Duration::seconds(seconds.round() as i64))
+}
+
+// Returns the pending tasks of the export, with their estimates read from the given UDA or else
+// from 'estimate' or 'duration'.
+pub fn parse_tasks(s: &str, uda: Option<&str>) -> Result<Vec<Task>, String> {
+    let parsed_json = match json::parse(s) {
+        Ok(val) => val,
+        Err(err) => {
+            return Err(format!(
+                "Unable to parse the task export\nError: '{}'\n",
+                err
+            ))
+        }
+    };
+    if !parsed_json.is_array() {
+        return Err("The task export is expected to be a JSON array of tasks\n".to_string());
+    }
+    let udas = match uda {
+        Some(val) => vec![val],
+        None => vec!["estimate", "duration"],
+    };
+
+    let mut tasks = Vec::new();
+    for jv in parsed_json.members() {
+        if jv["status"].as_str() != Some("pending") {
+            continue;
+        }
+        let mut tags: HashSet<String> = jv["tags"]
+            .members()
+            .filter_map(|tag| tag.as_str())
+            .map(|tag| tag.to_string())
+            .collect();
+        if let Some(project) = jv["project"].as_str() {
+            tags.insert(project.to_string());
+        }
+
+        let estimate = match udas.iter().map(|uda| &jv[*uda]).find(|jv| !jv.is_null()) {
+            None => None,
+            Some(value) => match parse_estimate(value) {
+                Some(val) => Some(val),
+                None => {
+                    return Err(format!(
+                        "Unable to parse the estimate {} of the task '{}'\n",
+                        value.dump(),
+                        jv["description"].as_str().unwrap_or_default()
+                    ))
+                }
+            },
+        };
+        let dates = ["due", "scheduled"]
+            .iter()
+            .filter_map(|key| jv[*key].as_str())
+            .filter_map(|value| NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ").ok())
+            .collect();
+
+        tasks.push(Task {
+            tagset: TagSet { tags },
+            estimate,
+            dates,
+        });
+    }
+    Ok(tasks)
+}
+
+// The plan proposed from the tasks, along with the number of tasks of the range left out of it.
+pub struct Proposal {
+    pub workgroups: Vec<WorkGroup>,
+    pub unestimated: usize,
+    pub untagged: usize,
+}
+
+impl Proposal {
+    // Returns the notes about the tasks left out of the plan.
+    pub fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if self.unestimated > 0 {
+            notes.push(format!(
+                "Left out {} tasks without an estimate",
+                self.unestimated
+            ));
+        }
+        if self.untagged > 0 {
+            notes.push(format!(
+                "Left out {} tasks without a project or tags",
+                self.untagged
+            ));
+        }
+        notes
+    }
+}
+
+// Proposes the workgroups of the range from the tasks due or scheduled in it. Every group of a
+// project and tags is allocated the sum of the estimates of its tasks, rounded up to a quarter of
+// an hour. The groups are sorted by name.
+pub fn propose(tasks: &[Task], start: NaiveDateTime, end: NaiveDateTime) -> Proposal {
+    let mut proposal = Proposal {
+        workgroups: Vec::new(),
+        unestimated: 0,
+        untagged: 0,
+    };
+    let mut estimates: Vec<(TagSet, Duration)> = Vec::new();
+    for task in tasks {
+        if !task.dates.iter().any(|date| *date >= start && *date < end) {
+            continue;
+        }
+        let estimate = match task.estimate {
+            Some(val) => val,
+            None => {
+                proposal.unestimated += 1;
+                continue;
+            }
+        };
+        if task.tagset.tags.is_empty() {
+            proposal.untagged += 1;
+            continue;
+        }
+        match estimates
+            .iter_mut()
+            .find(|(tagset, _)| tagset.tags == task.tagset.tags)
+        {
+            Some((_, total)) => *total = *total + estimate,
+            None => estimates.push((task.tagset.clone(), estimate)),
+        }
+    }
+
+    for (tagset, estimate) in estimates {
+        let quarters = (estimate.num_seconds() + 899) / 900;
+        if quarters > 0 {
+            proposal
+                .workgroups
+                .push(WorkGroup::new(tagset, Duration::minutes(quarters * 15)));
+        }
+    }
+    proposal
+        .workgroups
+        .sort_by_key(|workgroup| workgroup.name());
+    proposal
+}
+
+fn read_tasks(path: &str, uda: Option<&str>) -> Result<Vec<Task>, String> {
+    let contents = if path == "-" {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer).map(|_| buffer)
+    } else {
+        fs::read_to_string(path)
+    };
+    let contents = match contents {
+        Ok(val) => val,
+        Err(err) => {
+            return Err(format!(
+                "Unable to read the task export {}\nError: '{}'\n",
+                path, err
+            ))
+        }
+    };
+    match parse_tasks(&contents, uda) {
+        Ok(val) => Ok(val),
+        Err(err) => Err(format!("{}: {}", path, err)),
+    }
+}
+
+// Writes the plan proposed from the tasks as told by the arguments and returns the path it was
+// written to along with the proposal.
+pub fn plan_tasks(args: &[String]) -> Result<(String, Proposal), String> {
+    let (settings, rest) = Settings::parse_from_args(args)?;
+    let mut positional = Vec::new();
+    let mut week = false;
+    let mut force = false;
+    for arg in rest {
+        match arg {
+            "--week" => week = true,
+            "--force" => force = true,
+            _ => positional.push(arg),
+        }
+    }
+    let (export, period) = match positional.as_slice() {
+        [export] => (*export, ":week"),
+        [export, period] => (*export, *period),
+        _ => return Err(USAGE.to_string()),
+    };
+    let period = Period::parse_from_str(period, settings.today(), settings.week_start, week)?;
+
+    let uda = settings
+        .config
+        .get("aggregate.taskwarrior.estimate")
+        .map(|val| val.as_str());
+    let tasks = read_tasks(export, uda)?;
+    let (start, end) = period.range();
+    let proposal = propose(
+        &tasks,
+        timezone::utc_of_local_midnight(start),
+        timezone::utc_of_local_midnight(end),
+    );
+    if proposal.workgroups.is_empty() {
+        let notes: String = proposal
+            .notes()
+            .iter()
+            .map(|note| format!("{}\n", note))
+            .collect();
+        return Err(format!(
+            "No estimated tasks are due or scheduled from {} until {}\n{}",
+            start, end, notes
+        ));
+    }
+
+    let layout = WeekLayout::parse_from_config(
+        settings
+            .config
+            .get("aggregate.week.layout")
+            .map(|val| val.as_str()),
+    )?;
+    let path = workgroup::write_allocation_file(
+        &period.allocation_file_paths(&workgroup::config_dir(), layout),
+        &workgroup::to_allocation_json(&proposal.workgroups),
+        force,
+    )?;
+    Ok((path, proposal))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_estimate, parse_tasks, propose};
+    use chrono::NaiveDate;
+    use json::JsonValue;
+
+    fn minutes(s: &str) -> Option<i64> {
+        parse_estimate(&JsonValue::from(s)).map(|estimate| estimate.num_minutes())
+    }
+
+    #[test]
+    fn parse_estimates() {
+        assert_eq!(minutes("PT1H30M"), Some(90));
+        assert_eq!(minutes("P1DT2H"), Some(26 * 60));
+        assert_eq!(minutes("90min"), Some(90));
+        assert_eq!(minutes("1h30min"), Some(90));
+        assert_eq!(minutes("2.5"), Some(150));
+        assert_eq!(
+            parse_estimate(&JsonValue::from(0.25)).map(|val| val.num_minutes()),
+            Some(15)
+        );
+        assert_eq!(minutes("soon"), None);
+        assert_eq!(minutes("PT1X"), None);
+        assert_eq!(minutes(""), None);
+    }
+
+    #[test]
+    fn parse_pending_tasks() {
+        let s = "[
+{\"description\":\"write report\",\"status\":\"pending\",\"project\":\"office\",\"tags\":[\"project\"],\"due\":\"20210729T160000Z\",\"estimate\":\"PT2H\"},
+{\"description\":\"read book\",\"status\":\"pending\",\"tags\":[\"learning\"],\"scheduled\":\"20210727T080000Z\",\"duration\":1.5},
+{\"description\":\"old\",\"status\":\"completed\",\"project\":\"office\",\"estimate\":\"PT1H\"}
+]";
+        let tasks = parse_tasks(s, None).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks[0].tagset.tags.contains("office"));
+        assert!(tasks[0].tagset.tags.contains("project"));
+        assert_eq!(tasks[0].estimate.unwrap().num_minutes(), 120);
+        assert_eq!(tasks[1].estimate.unwrap().num_minutes(), 90);
+        assert_eq!(tasks[1].dates.len(), 1);
+
+        // Only the configured UDA is used.
+        let tasks = parse_tasks(s, Some("duration")).unwrap();
+        assert!(tasks[0].estimate.is_none());
+
+        let s = "[{\"description\":\"x\",\"status\":\"pending\",\"estimate\":\"soon\"}]";
+        assert!(parse_tasks(s, None).is_err());
+        assert!(parse_tasks("{}", None).is_err());
+    }
+
+    #[test]
+    fn propose_from_tasks() {
+        let s = "[
+{\"status\":\"pending\",\"project\":\"office\",\"tags\":[\"project\"],\"due\":\"20210729T160000Z\",\"estimate\":\"PT2H\"},
+{\"status\":\"pending\",\"project\":\"office\",\"tags\":[\"project\"],\"scheduled\":\"20210730T080000Z\",\"estimate\":\"PT10M\"},
+{\"status\":\"pending\",\"tags\":[\"learning\"],\"due\":\"20210727T080000Z\",\"estimate\":1},
+{\"status\":\"pending\",\"tags\":[\"learning\"],\"due\":\"20210803T080000Z\",\"estimate\":1},
+{\"status\":\"pending\",\"project\":\"office\",\"due\":\"20210728T080000Z\"},
+{\"status\":\"pending\",\"due\":\"20210728T080000Z\",\"estimate\":1}
+]";
+        let tasks = parse_tasks(s, None).unwrap();
+        let start = NaiveDate::from_ymd(2021, 7, 26).and_hms(0, 0, 0);
+        let end = NaiveDate::from_ymd(2021, 8, 2).and_hms(0, 0, 0);
+        let proposal = propose(&tasks, start, end);
+
+        assert_eq!(proposal.workgroups.len(), 2);
+        assert_eq!(proposal.workgroups[0].name(), "learning");
+        assert_eq!(proposal.workgroups[0].time_allocated.num_minutes(), 60);
+        // The estimates are summed up and rounded up to a quarter of an hour.
+        assert_eq!(proposal.workgroups[1].name(), "office project");
+        assert_eq!(proposal.workgroups[1].time_allocated.num_minutes(), 135);
+        assert_eq!(proposal.unestimated, 1);
+        assert_eq!(proposal.untagged, 1);
+        assert_eq!(proposal.notes().len(), 2);
+    }
+}
@@ -0,0 +1,142 @@
+// Rolls up the time tracked by several people, each with their own timewarrior, against a plan
+// shared by the team. Every member is aggregated on their own and all of them together.
+
+use crate::clock::Clock;
+use crate::export;
+use crate::report::{self, Report, ReportFormat};
+use crate::standalone;
+use crate::status;
+use crate::style::Style;
+use crate::twinput::TimeWarriorInput;
+use crate::workgroup::{self, WorkGroup};
+
+pub struct Member {
+    pub name: String,
+    pub twinput: TimeWarriorInput,
+}
+
+// Builds the members from arguments of the form <name>=<export.json>, the rest of the arguments
+// being the range and configuration overrides shared by everyone.
+pub fn load_members(args: &[String]) -> Result<Vec<Member>, String> {
+    let mut exports = Vec::new();
+    let mut rest = Vec::new();
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, path)) if !arg.starts_with("rc.") => exports.push((name, path)),
+            _ => rest.push(arg.clone()),
+        }
+    }
+    if exports.is_empty() {
+        return Err(
+            "No exports given, use <name>=<export.json> for each member of the team\n".to_string(),
+        );
+    }
+    // The standard input can only be read once.
+    if exports.iter().filter(|(_, path)| *path == "-").count() > 1 {
+        return Err("Only one member can read their export from the standard input\n".to_string());
+    }
+
+    let mut members = Vec::new();
+    for (name, path) in exports {
+        let twinput =
+            standalone::build_input(&rest, |clock: &Clock| export::read_export(path, clock))?;
+        members.push(Member {
+            name: name.to_string(),
+            twinput,
+        });
+    }
+    Ok(members)
+}
+
+// Returns the workgroups of each member followed by the workgroups of the whole team, all of them
+// starting from the same plan.
+pub fn roll_up(plan: &[WorkGroup], members: &[Member]) -> (Vec<Vec<WorkGroup>>, Vec<WorkGroup>) {
+    let mut team = plan.to_vec();
+    let mut per_member = Vec::new();
+    for member in members {
+        let mut workgroups = plan.to_vec();
+        workgroup::process(&member.twinput, &mut workgroups);
+        workgroup::process(&member.twinput, &mut team);
+        per_member.push(workgroups);
+    }
+    (per_member, team)
+}
+
+pub fn print_roll_up(
+    members: &[Member],
+    per_member: &[Vec<WorkGroup>],
+    team: &[WorkGroup],
+    style: &Style,
+) -> Result<(), String> {
+    let mut reports = Vec::new();
+    for (member, workgroups) in members.iter().zip(per_member) {
+        let mut report = Report::from_workgroups(Some(&member.name), workgroups);
+        match style.format {
+            ReportFormat::Svg => report.add_days(&member.twinput, workgroups)?,
+            ReportFormat::Status | ReportFormat::Waybar => {
+                report.active = status::active_group(&member.twinput, workgroups)
+            }
+            _ => {}
+        }
+        reports.push(report);
+    }
+    // The metrics of the team would be counted twice when summing up those of the members, and
+    // would collide with a member named team.
+    if style.format != ReportFormat::Prometheus {
+        reports.push(Report::from_workgroups(Some("team"), team));
+    }
+    let file = members[0].twinput.get_config("aggregate.report.file");
+    report::print_reports(&reports, style, file)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load_members, roll_up, Member};
+    use crate::testutil;
+    use crate::twinput::TimeWarriorInput;
+
+    fn member(name: &str, entries: &str) -> Member {
+        let s = format!(
+            "temp.report.start: 20210722T000000Z\ntemp.report.end: 20210723T000000Z\n\n[\n{}\n]\n",
+            entries
+        );
+        Member {
+            name: name.to_string(),
+            twinput: TimeWarriorInput::parse_from_str(&s).unwrap(),
+        }
+    }
+
+    #[test]
+    fn roll_up_members() {
+        let s = "[{\"tags\": [ \"office\", \"project\" ], \"allocation\": 4}, {\"tags\": [ \"office\", \"review\" ], \"allocation\": 1}]";
+        let plan = testutil::workgroups(s);
+
+        let alice = member(
+            "alice",
+            "{\"id\":1,\"start\":\"20210722T080000Z\",\"end\":\"20210722T100000Z\",\"tags\":[\"office\",\"project\"]}",
+        );
+        let bob = member(
+            "bob",
+            "{\"id\":2,\"start\":\"20210722T080000Z\",\"end\":\"20210722T090000Z\",\"tags\":[\"office\",\"project\"]},\n{\"id\":1,\"start\":\"20210722T090000Z\",\"end\":\"20210722T093000Z\",\"tags\":[\"office\",\"review\"]}",
+        );
+
+        let (per_member, team) = roll_up(&plan, &[alice, bob]);
+        assert_eq!(per_member.len(), 2);
+        assert_eq!(per_member[0][0].time_spent.num_minutes(), 120);
+        assert_eq!(per_member[0][1].time_spent.num_minutes(), 0);
+        assert_eq!(per_member[1][0].time_spent.num_minutes(), 60);
+        assert_eq!(per_member[1][1].time_spent.num_minutes(), 30);
+        assert_eq!(team[0].time_spent.num_minutes(), 180);
+        assert_eq!(team[1].time_spent.num_minutes(), 30);
+        assert_eq!(team[0].time_allocated.num_hours(), 4);
+    }
+
+    #[test]
+    fn load_members_single_stdin() {
+        let args: Vec<String> = vec!["alice=-".to_string(), "bob=-".to_string()];
+        assert_eq!(
+            load_members(&args).err().unwrap(),
+            "Only one member can read their export from the standard input\n"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+// Fixtures shared by the tests of the modules.
+
+use crate::clock::Clock;
+use crate::twentry::TimeWarriorEntry;
+use crate::twinput::TimeWarriorInput;
+use crate::workgroup::WorkGroup;
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+
+// Returns the interval between two UTC times, like '20210720T080000Z', with the tags as stored in
+// the data files.
+pub fn entry(start: &str, end: &str, tags: &str) -> TimeWarriorEntry {
+    let line = format!("inc {} - {} # {}", start, end, tags);
+    TimeWarriorEntry::parse_from_data_line(&line, &Clock::system()).unwrap()
+}
+
+// Builds the input for a range of UTC dates, which avoids depending on the local timezone.
+pub fn input_for_range(
+    start: NaiveDate,
+    end: NaiveDate,
+    twentries: &[TimeWarriorEntry],
+) -> TimeWarriorInput {
+    let start = start.and_hms(0, 0, 0);
+    let end = end.and_hms(0, 0, 0);
+    TimeWarriorInput {
+        start: start.format("%Y%m%dT%H%M%SZ").to_string(),
+        end: end.format("%Y%m%dT%H%M%SZ").to_string(),
+        config: HashMap::new(),
+        clock: Clock::fixed(NaiveDateTime::from_timestamp(0, 0)),
+        twentries: twentries
+            .iter()
+            .filter_map(|twentry| twentry.clone().clip(start, end))
+            .collect(),
+    }
+}
+
+pub fn config(settings: &[(&str, &str)]) -> HashMap<String, String> {
+    settings
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+// Returns the workgroups of the contents of an allocation file.
+pub fn workgroups(s: &str) -> Vec<WorkGroup> {
+    let jv = json::parse(s).unwrap();
+    jv.members().map(WorkGroup::parse_from_json_value).collect()
+}
@@ -0,0 +1,216 @@
+// Timewarrior passes the report range in UTC. The allocation files are organised by local dates, so
+// the range is mapped to local dates here. Days with a daylight saving change are 23 or 25 hours
+// long, which is why the length of the range is counted in local dates instead of hours.
+
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+#[cfg(test)]
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(test)]
+static TZ_LOCK: Mutex<()> = Mutex::new(());
+
+// Keeps the timezone set by lock_timezone until it's dropped, then puts the previous one back.
+#[cfg(test)]
+pub struct TimezoneGuard {
+    previous: Option<OsString>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+impl Drop for TimezoneGuard {
+    fn drop(&mut self) {
+        apply_timezone(self.previous.as_ref());
+    }
+}
+
+// Sets the timezone of the process for a test which depends on the local time, holding a lock for
+// as long as the guard lives so that tests running in parallel don't change it underneath.
+#[cfg(test)]
+pub fn lock_timezone(tz: &str) -> TimezoneGuard {
+    let lock = TZ_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let previous = env::var_os("TZ");
+    set_timezone(tz).unwrap();
+    TimezoneGuard {
+        previous,
+        _lock: lock,
+    }
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn tzset();
+}
+
+// Returns the file of the timezone in the timezone database, which is looked up in TZDIR first like
+// libc does, since it isn't at /usr/share/zoneinfo on every system.
+fn zoneinfo_path(tzdir: Option<OsString>, tz: &str) -> PathBuf {
+    let dir = match tzdir {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from("/usr/share/zoneinfo"),
+    };
+    dir.join(tz)
+}
+
+#[cfg(unix)]
+fn apply_timezone(tz: Option<&OsString>) {
+    match tz {
+        Some(tz) => env::set_var("TZ", tz),
+        None => env::remove_var("TZ"),
+    }
+    unsafe { tzset() };
+}
+
+#[cfg(all(test, not(unix)))]
+fn apply_timezone(_tz: Option<&OsString>) {}
+
+// Makes the given timezone (like Europe/Berlin) the local timezone of the process. The standard
+// library reads TZ only once, hence tzset is called to pick up the change.
+#[cfg(unix)]
+pub fn set_timezone(tz: &str) -> Result<(), String> {
+    if !zoneinfo_path(env::var_os("TZDIR"), tz).is_file() {
+        return Err(format!(
+            "Unknown timezone '{}' in aggregate.timezone. Expected a name like 'Europe/Berlin'.\n",
+            tz
+        ));
+    }
+    apply_timezone(Some(&OsString::from(tz)));
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn set_timezone(tz: &str) -> Result<(), String> {
+    Err(format!(
+        "Unable to use the timezone '{}' in aggregate.timezone, which is only supported on Unix.\n",
+        tz
+    ))
+}
+
+pub fn local_date(utc: &NaiveDateTime) -> NaiveDate {
+    Local.from_utc_datetime(utc).date().naive_local()
+}
+
+// Returns the UTC time at which the given local day starts. If midnight is skipped by a daylight
+// saving change, the day starts an hour later.
+pub fn utc_of_local_midnight(day: NaiveDate) -> NaiveDateTime {
+    let midnight = day.and_hms(0, 0, 0);
+    match Local.from_local_datetime(&midnight).earliest() {
+        Some(val) => val.naive_utc(),
+        None => Local
+            .from_local_datetime(&day.and_hms(1, 0, 0))
+            .earliest()
+            .unwrap()
+            .naive_utc(),
+    }
+}
+
+// Returns the local dates of the start and the end of a range given in timewarrior's UTC format
+// along with the number of local days in between.
+pub fn local_range(start: &str, end: &str) -> Result<(NaiveDate, NaiveDate, i64), String> {
+    let start = match NaiveDateTime::parse_from_str(start, "%Y%m%dT%H%M%SZ") {
+        Ok(val) => val,
+        Err(_) => {
+            return Err(format!(
+                "Unable to parse the start of the range '{}'\n",
+                start
+            ))
+        }
+    };
+    let end = match NaiveDateTime::parse_from_str(end, "%Y%m%dT%H%M%SZ") {
+        Ok(val) => val,
+        Err(_) => return Err(format!("Unable to parse the end of the range '{}'\n", end)),
+    };
+    let local_start = local_date(&start);
+    let local_end = local_date(&end);
+    // Only whole local days map to allocation files.
+    if utc_of_local_midnight(local_start) != start || utc_of_local_midnight(local_end) != end {
+        return Err(format!(
+            "The range from {} to {} doesn't start and end at local midnight\n",
+            start.format("%Y%m%dT%H%M%SZ"),
+            end.format("%Y%m%dT%H%M%SZ")
+        ));
+    }
+    let days = local_end.signed_duration_since(local_start).num_days();
+    Ok((local_start, local_end, days))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{local_range, lock_timezone, set_timezone, utc_of_local_midnight, zoneinfo_path};
+    use chrono::NaiveDate;
+    use std::ffi::OsString;
+    use std::path::Path;
+
+    #[test]
+    fn local_range_spring_forward() {
+        let _lock = lock_timezone("Europe/Berlin");
+
+        // 2021-03-28 is 23 hours long in Berlin.
+        let (start, end, days) = local_range("20210327T230000Z", "20210328T220000Z").unwrap();
+        assert_eq!(start, NaiveDate::from_ymd(2021, 3, 28));
+        assert_eq!(end, NaiveDate::from_ymd(2021, 3, 29));
+        assert_eq!(days, 1);
+
+        let (start, _, days) = local_range("20210321T230000Z", "20210328T220000Z").unwrap();
+        assert_eq!(start, NaiveDate::from_ymd(2021, 3, 22));
+        assert_eq!(days, 7);
+    }
+
+    #[test]
+    fn local_range_fall_back() {
+        let _lock = lock_timezone("America/New_York");
+
+        // 2021-11-07 is 25 hours long in New York.
+        let (start, end, days) = local_range("20211107T040000Z", "20211108T050000Z").unwrap();
+        assert_eq!(start, NaiveDate::from_ymd(2021, 11, 7));
+        assert_eq!(end, NaiveDate::from_ymd(2021, 11, 8));
+        assert_eq!(days, 1);
+    }
+
+    #[test]
+    fn local_range_not_midnight() {
+        let _lock = lock_timezone("Europe/Berlin");
+
+        assert!(local_range("20210721T080000Z", "20210722T070000Z").is_err());
+        assert!(local_range("20210720T220000Z", "20210721T230000Z").is_err());
+        assert!(local_range("20210720T220000Z", "20210721T220000Z").is_ok());
+    }
+
+    #[test]
+    fn utc_of_local_midnight_spring_forward() {
+        let _lock = lock_timezone("Europe/Berlin");
+
+        let start = utc_of_local_midnight(NaiveDate::from_ymd(2021, 3, 28));
+        let end = utc_of_local_midnight(NaiveDate::from_ymd(2021, 3, 29));
+        assert_eq!(
+            start.format("%Y%m%dT%H%M%SZ").to_string(),
+            "20210327T230000Z"
+        );
+        assert_eq!(end.format("%Y%m%dT%H%M%SZ").to_string(), "20210328T220000Z");
+    }
+
+    #[test]
+    fn set_timezone_unknown() {
+        assert!(set_timezone("Nowhere/Atlantis").is_err());
+    }
+
+    #[test]
+    fn zoneinfo_path_tzdir() {
+        assert_eq!(
+            zoneinfo_path(None, "Europe/Berlin"),
+            Path::new("/usr/share/zoneinfo/Europe/Berlin")
+        );
+        assert_eq!(
+            zoneinfo_path(Some(OsString::new()), "Europe/Berlin"),
+            Path::new("/usr/share/zoneinfo/Europe/Berlin")
+        );
+        let tzdir = OsString::from("/run/current-system/sw/share/zoneinfo");
+        assert_eq!(
+            zoneinfo_path(Some(tzdir), "Europe/Berlin"),
+            Path::new("/run/current-system/sw/share/zoneinfo/Europe/Berlin")
+        );
+    }
+}
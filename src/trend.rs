@@ -0,0 +1,253 @@
+// Reports how much of its allocation every group used in each of the last weeks, along with the
+// average and the standard deviation over those weeks. The results of the weeks are taken from the
+// history and computed from the timewarrior database for the weeks missing in it.
+
+use crate::history;
+use crate::standalone::Settings;
+use crate::style::Style;
+use crate::table::{Cell, Table};
+use crate::twdb;
+use crate::workgroup::{self, WorkGroup};
+use chrono::{Duration, NaiveDate};
+
+pub struct TrendRow {
+    pub name: String,
+    // The percentage of the allocation used in each week, if the group was planned for the week.
+    pub percentages: Vec<Option<f64>>,
+}
+
+impl TrendRow {
+    fn planned(&self) -> Vec<f64> {
+        self.percentages.iter().flatten().cloned().collect()
+    }
+
+    pub fn average(&self) -> Option<f64> {
+        let planned = self.planned();
+        if planned.is_empty() {
+            return None;
+        }
+        Some(planned.iter().sum::<f64>() / planned.len() as f64)
+    }
+
+    pub fn std_dev(&self) -> Option<f64> {
+        let planned = self.planned();
+        let average = self.average()?;
+        let variance = planned
+            .iter()
+            .map(|percentage| (percentage - average).powi(2))
+            .sum::<f64>()
+            / planned.len() as f64;
+        Some(variance.sqrt())
+    }
+}
+
+pub struct Trend {
+    pub weeks: Vec<NaiveDate>,
+    pub rows: Vec<TrendRow>,
+}
+
+impl Trend {
+    // Builds the trend from the results of the weeks, None being a week without a plan. Groups
+    // without any allocation in a week are left out of that week.
+    pub fn from_results(weeks: Vec<NaiveDate>, results: &[Option<Vec<WorkGroup>>]) -> Self {
+        let mut rows: Vec<TrendRow> = Vec::new();
+        for (index, result) in results.iter().enumerate() {
+            for workgroup in result.iter().flatten() {
+                let allocated = workgroup.time_allocated.num_seconds();
+                if allocated == 0 {
+                    continue;
+                }
+                let percentage =
+                    workgroup.time_spent.num_seconds() as f64 * 100.0 / allocated as f64;
+                let name = workgroup.name();
+                let position = match rows.iter().position(|row| row.name == name) {
+                    Some(val) => val,
+                    None => {
+                        rows.push(TrendRow {
+                            name,
+                            percentages: vec![None; weeks.len()],
+                        });
+                        rows.len() - 1
+                    }
+                };
+                rows[position].percentages[index] = Some(percentage);
+            }
+        }
+        Trend { weeks, rows }
+    }
+}
+
+// Loads the trend of the last finished weeks, four unless the number of weeks is given, along with
+// the settings it was loaded with.
+pub fn load_trend(args: &[String]) -> Result<(Trend, Settings), String> {
+    let (settings, rest) = Settings::parse_from_args(args)?;
+    let count = match rest.as_slice() {
+        [] => 4,
+        [count] => match count.parse::<i64>() {
+            Ok(val) if val > 0 => val,
+            _ => return Err(format!("Invalid number of weeks '{}'\n", count)),
+        },
+        _ => return Err("Usage: aggregate trend [<weeks>] [rc.<name>=<value> ...]\n".to_string()),
+    };
+
+    let this_week = settings.week_start_of(settings.today());
+    let weeks: Vec<NaiveDate> = (1..=count)
+        .rev()
+        .map(|n| this_week - Duration::days(7 * n))
+        .collect();
+
+    let (records, _) = history::read_records();
+    let mut twentries = None;
+    let mut results = Vec::new();
+    let mut new_records = Vec::new();
+    for week in weeks.iter() {
+        let end = *week + Duration::days(7);
+        let record = records
+            .iter()
+            .find(|record| record.start == *week && record.end == end);
+        if let Some(record) = record {
+            results.push(Some(record.workgroups.clone()));
+            continue;
+        }
+
+        // Weeks without a plan are left out of the trend, but a plan which can't be loaded is
+        // reported.
+        let twinput = settings.input_for_range(*week, end, &[]);
+        let (paths, _) = workgroup::allocation_file_paths(&twinput)?;
+        let (path, contents) = match workgroup::read_first_existing(&paths) {
+            Some(val) => val,
+            None => {
+                results.push(None);
+                continue;
+            }
+        };
+        let mut workgroups = match workgroup::parse_allocation(&path, &contents) {
+            Ok(val) => val,
+            Err(err) => {
+                eprintln!("Skipping the week of {}: {}", week, err.trim_end());
+                results.push(None);
+                continue;
+            }
+        };
+
+        if twentries.is_none() {
+            twentries = Some(twdb::read_entries(&twdb::db_dir(), &settings.clock)?);
+        }
+        let twinput = settings.input_for_range(*week, end, twentries.as_deref().unwrap_or(&[]));
+        workgroup::process(&twinput, &mut workgroups);
+        match history::finished_record(&twinput, &workgroups) {
+            Ok(Some(record)) => new_records.push(record),
+            Ok(None) => {}
+            Err(err) => eprint!("{}", err),
+        }
+        results.push(Some(workgroups));
+    }
+    // The history only saves going through the intervals again, so the trend doesn't depend on it.
+    if let Err(err) = history::record_all(new_records) {
+        eprint!("{}", err);
+    }
+    Ok((Trend::from_results(weeks, &results), settings))
+}
+
+fn format_percentage(percentage: Option<f64>) -> String {
+    match percentage {
+        Some(val) => format!("{:.0}%", val),
+        None => "-".to_string(),
+    }
+}
+
+// Returns the trend as a table, with a column for every week.
+pub fn to_table(trend: &Trend) -> Table {
+    let mut header = vec!["group".to_string()];
+    header.extend(
+        trend
+            .weeks
+            .iter()
+            .map(|week| week.format("%m-%d").to_string()),
+    );
+    header.push("average".to_string());
+    header.push("std dev".to_string());
+    let mut table = Table::new(
+        &header
+            .iter()
+            .map(|title| title.as_str())
+            .collect::<Vec<&str>>(),
+    );
+    for row in trend.rows.iter() {
+        let mut cells = vec![Cell::new(&row.name)];
+        for percentage in row.percentages.iter() {
+            cells.push(Cell::new(&format_percentage(*percentage)));
+        }
+        cells.push(Cell::new(&format_percentage(row.average())));
+        cells.push(Cell::new(&format_percentage(row.std_dev())));
+        table.push(cells);
+    }
+    table
+}
+
+pub fn print_trend(trend: &Trend, style: &Style) {
+    for line in to_table(trend).render(style, false) {
+        println!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{to_table, Trend};
+    use crate::style::Style;
+    use crate::testutil;
+    use crate::workgroup::WorkGroup;
+    use chrono::{Duration, NaiveDate};
+
+    fn workgroups(allocations: &str, spent: &[i64]) -> Vec<WorkGroup> {
+        let mut workgroups = testutil::workgroups(allocations);
+        for (workgroup, spent) in workgroups.iter_mut().zip(spent) {
+            workgroup.time_spent = Duration::hours(*spent);
+        }
+        workgroups
+    }
+
+    #[test]
+    fn from_results() {
+        let weeks = vec![
+            NaiveDate::from_ymd(2021, 7, 12),
+            NaiveDate::from_ymd(2021, 7, 19),
+            NaiveDate::from_ymd(2021, 7, 26),
+        ];
+        let plan = "[{\"tags\": [\"office\", \"project\"], \"allocation\": 10}, {\"tags\": [\"office\", \"review\"], \"allocation\": 4}]";
+        let results = vec![
+            Some(workgroups(plan, &[5, 4])),
+            None,
+            Some(workgroups(plan, &[15, 2])),
+        ];
+        let trend = Trend::from_results(weeks, &results);
+
+        assert_eq!(trend.rows.len(), 2);
+        assert_eq!(trend.rows[0].name, "office project");
+        assert_eq!(
+            trend.rows[0].percentages,
+            vec![Some(50.0), None, Some(150.0)]
+        );
+        assert_eq!(trend.rows[0].average(), Some(100.0));
+        assert_eq!(trend.rows[0].std_dev(), Some(50.0));
+        assert_eq!(trend.rows[1].average(), Some(75.0));
+        assert_eq!(trend.rows[1].std_dev(), Some(25.0));
+    }
+
+    #[test]
+    fn to_table_long_names() {
+        let weeks = vec![NaiveDate::from_ymd(2021, 7, 19)];
+        let plan = "[{\"tags\": [\"learning\", \"personal\", \"Rust talks\"], \"allocation\": 4}]";
+        let trend = Trend::from_results(weeks, &[Some(workgroups(plan, &[3]))]);
+
+        let lines = to_table(&trend).render(&Style::plain(), false);
+        assert_eq!(
+            lines[0],
+            "| group                        | 07-19 | average | std dev"
+        );
+        assert_eq!(
+            lines[1],
+            "| Rust talks learning personal | 75%   | 75%     | 0%"
+        );
+    }
+}
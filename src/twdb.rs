@@ -0,0 +1,100 @@
+// Access to the timewarrior database for running without timewarrior. The database is located at
+// $TIMEWARRIORDB or ~/.timewarrior and holds the configuration in timewarrior.cfg and the intervals
+// in data/<year>-<month>.data.
+
+use crate::clock::Clock;
+use crate::twentry::TimeWarriorEntry;
+use log::debug;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn db_dir() -> PathBuf {
+    match env::var("TIMEWARRIORDB") {
+        Ok(val) => PathBuf::from(val),
+        Err(_) => PathBuf::from(env::var("HOME").unwrap()).join(".timewarrior"),
+    }
+}
+
+// Parses the settings of the form 'key = value' in timewarrior.cfg. A missing file is the same as
+// an empty configuration, like it is for timewarrior.
+pub fn parse_config(s: &str) -> HashMap<String, String> {
+    let mut config = HashMap::new();
+    for line in s.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            config.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    config
+}
+
+pub fn read_config(db_dir: &Path) -> HashMap<String, String> {
+    let path = db_dir.join("timewarrior.cfg");
+    debug!("Reading timewarrior configuration at {}", path.display());
+    match fs::read_to_string(&path) {
+        Ok(val) => parse_config(&val),
+        Err(_) => HashMap::new(),
+    }
+}
+
+pub fn read_entries(db_dir: &Path, clock: &Clock) -> Result<Vec<TimeWarriorEntry>, String> {
+    let data_dir = db_dir.join("data");
+    let dir = match fs::read_dir(&data_dir) {
+        Ok(val) => val,
+        Err(err) => {
+            return Err(format!(
+                "Unable to read the timewarrior data directory at {}\nError: '{}'\n",
+                data_dir.display(),
+                err
+            ))
+        }
+    };
+
+    let mut twentries = Vec::new();
+    for dir_entry in dir.flatten() {
+        let path = dir_entry.path();
+        if path.extension() != Some(OsStr::new("data")) {
+            continue;
+        }
+        debug!("Reading intervals from {}", path.display());
+        let contents = match fs::read_to_string(&path) {
+            Ok(val) => val,
+            Err(err) => {
+                return Err(format!(
+                    "Unable to read {}\nError: '{}'\n",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        // Only the intervals are of interest, other files like undo.data and tags.data don't have
+        // lines starting with 'inc'.
+        for line in contents.lines().filter(|line| line.starts_with("inc ")) {
+            twentries.push(TimeWarriorEntry::parse_from_data_line(line, clock)?);
+        }
+    }
+    twentries.sort_by_key(|twentry| twentry.start);
+    Ok(twentries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_config;
+
+    #[test]
+    fn parse_config_settings() {
+        let config = parse_config(
+            "# Timewarrior configuration\nimport /usr/share/doc/timew/themes/dark.theme\naggregate.week.start = sunday\nverbose=no\n",
+        );
+
+        assert_eq!(config.len(), 2);
+        assert_eq!(config["aggregate.week.start"], "sunday");
+        assert_eq!(config["verbose"], "no");
+    }
+}
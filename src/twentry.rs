@@ -1,34 +1,104 @@
-use chrono::{Local, NaiveDateTime};
+use chrono::NaiveDateTime;
 
+use crate::clock::Clock;
 use crate::tagset::TagSet;
 use json::JsonValue;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TimeWarriorEntry {
     pub tagset: TagSet,
     pub start: NaiveDateTime,
     pub end: NaiveDateTime,
+    // Whether the interval is still going on, its end being the current time.
+    pub open: bool,
 }
 
 impl TimeWarriorEntry {
-    pub fn parse_from_json_value(jv: &JsonValue) -> Self {
-        // Get the tags from the entry.
-        let tagset = TagSet::parse_from_json_value(&jv);
-
-        // Get the starting time for the entry.
-        let start = jv["start"].as_str().unwrap();
-        let start = NaiveDateTime::parse_from_str(start, "%Y%m%dT%H%M%SZ").unwrap();
-
-        // Get the ending time for the entry. If end doesn't exist in the entry, then current time
-        // is the ending time for the entry (it is still going on).
-        let end;
-        if let Some(value) = jv["end"].as_str() {
-            end = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ").unwrap();
-        } else {
-            end = Local::now().naive_utc();
+    // Parses an interval as passed to extensions and given by 'timew export'. The problem which
+    // keeps the interval from being parsed is returned for the caller to say where it comes from.
+    pub fn parse_from_json_value(jv: &JsonValue, clock: &Clock) -> Result<Self, String> {
+        let parse = |jv: &JsonValue| {
+            jv.as_str()
+                .and_then(|value| NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ").ok())
+        };
+        let start = match parse(&jv["start"]) {
+            Some(val) => val,
+            None => {
+                return Err("the start is missing or isn't a time like 20210722T152328Z".to_string())
+            }
+        };
+
+        // If end doesn't exist in the entry, then current time as told by the clock is the ending
+        // time for the entry (it is still going on).
+        let open = jv["end"].is_null();
+        let end = match parse(&jv["end"]) {
+            Some(val) => val,
+            None if open => clock.now(),
+            None => return Err("the end isn't a time like 20210722T152328Z".to_string()),
+        };
+
+        if jv["tags"].members().any(|tag| !tag.is_string()) {
+            return Err("a tag isn't a string".to_string());
         }
+        Ok(TimeWarriorEntry {
+            tagset: TagSet::parse_from_json_value(jv),
+            start,
+            end,
+            open,
+        })
+    }
 
-        TimeWarriorEntry { tagset, start, end }
+    // Parses an interval as stored in the timewarrior data files, like
+    // 'inc 20210722T152328Z - 20210722T153753Z # learning personal'. The ending time is missing for
+    // the interval which is still going on.
+    pub fn parse_from_data_line(line: &str, clock: &Clock) -> Result<Self, String> {
+        let error_msg = format!("Unable to parse the interval '{}'", line);
+        let rest = match line.strip_prefix("inc ") {
+            Some(val) => val,
+            None => return Err(error_msg),
+        };
+        let (range, tags) = match rest.split_once('#') {
+            Some((range, tags)) => (range, tags),
+            None => (rest, ""),
+        };
+        let tagset = TagSet::parse_from_data_str(tags);
+
+        let range: Vec<&str> = range.split_whitespace().collect();
+        let parse = |value: &str| NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ");
+        let open = range.len() == 1;
+        let (start, end) = match range.as_slice() {
+            [start] => (parse(start), Ok(clock.now())),
+            [start, "-", end] => (parse(start), parse(end)),
+            _ => return Err(error_msg),
+        };
+        match (start, end) {
+            (Ok(start), Ok(end)) => Ok(TimeWarriorEntry {
+                tagset,
+                start,
+                end,
+                open,
+            }),
+            _ => Err(error_msg),
+        }
+    }
+
+    // Returns whether a part of the entry lies within the given range.
+    pub fn overlaps(&self, start: NaiveDateTime, end: NaiveDateTime) -> bool {
+        self.end > start && self.start < end
+    }
+
+    // Returns the part of the entry which lies within the given range, if any. An open interval cut
+    // off at the end of the range is no longer going on within it.
+    pub fn clip(mut self, start: NaiveDateTime, end: NaiveDateTime) -> Option<Self> {
+        if !self.overlaps(start, end) {
+            return None;
+        }
+        self.start = self.start.max(start);
+        if end < self.end {
+            self.end = end;
+            self.open = false;
+        }
+        Some(self)
     }
 
     pub fn duration(&self) -> chrono::Duration {
@@ -39,12 +109,14 @@ impl TimeWarriorEntry {
 #[cfg(test)]
 mod test {
     use super::TimeWarriorEntry;
+    use crate::clock::Clock;
+    use chrono::NaiveDateTime;
 
     #[test]
     fn parse_from_json_value_with_end() {
         let s = "{\"id\":3,\"start\":\"20210722T152328Z\",\"end\":\"20210722T153753Z\",\"tags\":[\"Rust talks\",\"learning\",\"personal\"]}";
-        let jv = json::parse(&s).unwrap();
-        let twe = TimeWarriorEntry::parse_from_json_value(&jv);
+        let jv = json::parse(s).unwrap();
+        let twe = TimeWarriorEntry::parse_from_json_value(&jv, &Clock::system()).unwrap();
 
         assert_eq!(twe.tagset.tags.len(), 3);
         assert!(twe.tagset.tags.contains("personal"));
@@ -59,13 +131,14 @@ mod test {
             twe.end.format("%Y-%m-%d %H:%M:%S").to_string(),
             "2021-07-22 15:37:53"
         );
+        assert!(!twe.open);
     }
 
     #[test]
     fn parse_from_json_value_without_end() {
         let s = "{\"id\":3,\"start\":\"20210722T152328Z\",\"tags\":[\"Rust talks\",\"learning\",\"personal\"]}";
-        let jv = json::parse(&s).unwrap();
-        let twe = TimeWarriorEntry::parse_from_json_value(&jv);
+        let jv = json::parse(s).unwrap();
+        let twe = TimeWarriorEntry::parse_from_json_value(&jv, &Clock::system()).unwrap();
 
         assert_eq!(twe.tagset.tags.len(), 3);
         assert!(twe.tagset.tags.contains("personal"));
@@ -76,13 +149,95 @@ mod test {
             twe.start.format("%Y-%m-%d %H:%M:%S").to_string(),
             "2021-07-22 15:23:28"
         );
+        assert!(twe.open);
+    }
+
+    #[test]
+    fn parse_from_json_value_without_end_fixed_clock() {
+        let s = "{\"id\":3,\"start\":\"20210722T152328Z\",\"tags\":[\"Rust talks\",\"learning\",\"personal\"]}";
+        let jv = json::parse(s).unwrap();
+        let now = NaiveDateTime::parse_from_str("20210722T163328Z", "%Y%m%dT%H%M%SZ").unwrap();
+        let twe = TimeWarriorEntry::parse_from_json_value(&jv, &Clock::fixed(now)).unwrap();
+
+        assert_eq!(twe.end, now);
+        assert_eq!(twe.duration().num_minutes(), 70);
+    }
+
+    #[test]
+    fn parse_from_json_value_invalid() {
+        let parse = |s: &str| {
+            TimeWarriorEntry::parse_from_json_value(&json::parse(s).unwrap(), &Clock::system())
+                .unwrap_err()
+        };
+        assert!(parse("{\"start\":\"yesterday\"}").starts_with("the start is missing"));
+        assert!(parse("{\"start\":\"20210722T152328Z\",\"end\":1}").starts_with("the end isn't"));
+        assert_eq!(
+            parse("{\"start\":\"20210722T152328Z\",\"tags\":[\"office\",2]}"),
+            "a tag isn't a string"
+        );
     }
 
     #[test]
     fn duration() {
         let s = "{\"id\":3,\"start\":\"20210722T152328Z\",\"end\":\"20210722T153753Z\",\"tags\":[\"Rust talks\",\"learning\",\"personal\"]}";
-        let jv = json::parse(&s).unwrap();
-        let twe = TimeWarriorEntry::parse_from_json_value(&jv);
+        let jv = json::parse(s).unwrap();
+        let twe = TimeWarriorEntry::parse_from_json_value(&jv, &Clock::system()).unwrap();
         assert_eq!(twe.duration().num_seconds(), 865);
     }
+
+    #[test]
+    fn parse_from_data_line() {
+        let line = "inc 20210722T152328Z - 20210722T153753Z # \"Rust talks\" learning personal";
+        let twe = TimeWarriorEntry::parse_from_data_line(line, &Clock::system()).unwrap();
+
+        assert_eq!(twe.tagset.tags.len(), 3);
+        assert!(twe.tagset.tags.contains("Rust talks"));
+        assert_eq!(twe.duration().num_seconds(), 865);
+
+        let now = NaiveDateTime::parse_from_str("20210722T163328Z", "%Y%m%dT%H%M%SZ").unwrap();
+        let line = "inc 20210722T152328Z # learning personal";
+        let twe = TimeWarriorEntry::parse_from_data_line(line, &Clock::fixed(now)).unwrap();
+        assert_eq!(twe.end, now);
+        assert!(twe.open);
+
+        let line = "inc 20210722T152328Z - 20210722T153753Z";
+        let twe = TimeWarriorEntry::parse_from_data_line(line, &Clock::system()).unwrap();
+        assert!(twe.tagset.tags.is_empty());
+
+        assert!(TimeWarriorEntry::parse_from_data_line("inc yesterday", &Clock::system()).is_err());
+    }
+
+    #[test]
+    fn clip() {
+        let line = "inc 20210722T220000Z - 20210723T020000Z # learning personal";
+        let start = NaiveDateTime::parse_from_str("20210723T000000Z", "%Y%m%dT%H%M%SZ").unwrap();
+        let end = NaiveDateTime::parse_from_str("20210724T000000Z", "%Y%m%dT%H%M%SZ").unwrap();
+
+        let twe = TimeWarriorEntry::parse_from_data_line(line, &Clock::system()).unwrap();
+        let twe = twe.clip(start, end).unwrap();
+        assert_eq!(twe.start, start);
+        assert_eq!(twe.duration().num_hours(), 2);
+
+        let twe = TimeWarriorEntry::parse_from_data_line(line, &Clock::system()).unwrap();
+        assert!(twe.clip(end, end + chrono::Duration::days(1)).is_none());
+    }
+
+    #[test]
+    fn clip_open() {
+        let now = NaiveDateTime::parse_from_str("20210723T020000Z", "%Y%m%dT%H%M%SZ").unwrap();
+        let line = "inc 20210722T220000Z # learning personal";
+        let start = NaiveDateTime::parse_from_str("20210722T000000Z", "%Y%m%dT%H%M%SZ").unwrap();
+        let end = NaiveDateTime::parse_from_str("20210723T000000Z", "%Y%m%dT%H%M%SZ").unwrap();
+
+        // Cut off at the end of the day, the interval isn't going on within it.
+        let twe = TimeWarriorEntry::parse_from_data_line(line, &Clock::fixed(now)).unwrap();
+        let twe = twe.clip(start, end).unwrap();
+        assert_eq!(twe.end, end);
+        assert!(!twe.open);
+
+        let twe = TimeWarriorEntry::parse_from_data_line(line, &Clock::fixed(now)).unwrap();
+        let twe = twe.clip(end, end + chrono::Duration::days(1)).unwrap();
+        assert_eq!(twe.end, now);
+        assert!(twe.open);
+    }
 }
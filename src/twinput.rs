@@ -1,38 +1,60 @@
+use crate::clock::Clock;
 use crate::twentry::TimeWarriorEntry;
 use log::debug;
+use std::collections::HashMap;
 
 pub struct TimeWarriorInput {
     pub start: String,
     pub end: String,
+    pub config: HashMap<String, String>,
+    pub clock: Clock,
     pub twentries: Vec<TimeWarriorEntry>,
 }
 
 impl TimeWarriorInput {
-    pub fn parse_from_str(s: &String) -> Result<Self, String> {
-        let mut twentries = Vec::new();
-        let mut start = String::from("");
-        let mut end = String::from("");
+    pub fn parse_from_str(s: &str) -> Result<Self, String> {
+        let mut entry_lines = Vec::new();
+        let mut config = HashMap::new();
         for line in s.lines() {
-            if line.starts_with("{") {
-                debug!("line {}", line);
-                let line = match line.strip_suffix(",") {
-                    Some(val) => val,
-                    None => line,
-                };
-                let jv = json::parse(line).expect("Unable to parse json");
-                let twentry = TimeWarriorEntry::parse_from_json_value(&jv);
-                twentries.push(twentry)
-            } else if line.starts_with("temp.report.start") {
-                let split = line.split(" ");
-                let vec: Vec<&str> = split.collect();
-                start = vec[1].to_string();
-            } else if line.starts_with("temp.report.end") {
-                let split = line.split(" ");
-                let vec: Vec<&str> = split.collect();
-                end = vec[1].to_string();
+            if line.starts_with('{') {
+                entry_lines.push(line);
+            } else if let Some((key, value)) = line.split_once(':') {
+                // Everything in the header is a configuration setting of the form 'key: value',
+                // including the report range passed as temp.report.start and temp.report.end. The
+                // value may be empty, leaving only 'key:'.
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                config.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        // The entries are parsed once the configuration is known since it may override the clock
+        // used for the entry which is still going on.
+        let clock = Clock::from_config(config.get("aggregate.now").map(|val| val.as_str()))?;
+        let mut twentries = Vec::new();
+        for line in entry_lines {
+            debug!("line {}", line);
+            let line = match line.strip_suffix(',') {
+                Some(val) => val,
+                None => line,
+            };
+            let jv = match json::parse(line) {
+                Ok(val) => val,
+                Err(err) => {
+                    return Err(format!(
+                        "Unable to parse the interval {}\nError: '{}'\n",
+                        line, err
+                    ))
+                }
+            };
+            match TimeWarriorEntry::parse_from_json_value(&jv, &clock) {
+                Ok(twentry) => twentries.push(twentry),
+                Err(problem) => return Err(format!("Invalid interval, {}: {}\n", problem, line)),
             }
         }
-        if start.len() == 0 || end.len() == 0 {
+
+        let start = config.get("temp.report.start").cloned().unwrap_or_default();
+        let end = config.get("temp.report.end").cloned().unwrap_or_default();
+        if start.is_empty() || end.is_empty() {
             return Err(
                 "Unable to find timewarrior passed statistics in standard input.\nWas this program run directly? This program is supposed to be invoked by timewarrior.\n".to_string()
             );
@@ -40,7 +62,64 @@ impl TimeWarriorInput {
         Ok(TimeWarriorInput {
             start,
             end,
+            config,
+            clock,
             twentries,
         })
     }
+
+    pub fn get_config(&self, key: &str) -> Option<&str> {
+        self.config.get(key).map(|value| value.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TimeWarriorInput;
+
+    static INPUT: &str = "temp.report.start: 20210722T183000Z
+temp.report.end: 20210723T183000Z
+aggregate.week.layout: iso
+aggregate.now: 20210722T180000Z
+
+[
+{\"id\":2,\"start\":\"20210722T152328Z\",\"end\":\"20210722T153753Z\",\"tags\":[\"learning\",\"personal\"]},
+{\"id\":1,\"start\":\"20210722T160000Z\",\"tags\":[\"office\",\"project\"]}
+]
+";
+
+    #[test]
+    fn parse_from_str() {
+        let twinput = TimeWarriorInput::parse_from_str(INPUT).unwrap();
+
+        assert_eq!(twinput.start, "20210722T183000Z");
+        assert_eq!(twinput.end, "20210723T183000Z");
+        assert_eq!(twinput.twentries.len(), 2);
+        assert_eq!(twinput.get_config("aggregate.week.layout"), Some("iso"));
+        assert_eq!(twinput.get_config("aggregate.missing"), None);
+
+        // The entry which is still going on ends at the configured time.
+        assert_eq!(twinput.twentries[1].duration().num_hours(), 2);
+    }
+
+    #[test]
+    fn parse_from_str_empty_value() {
+        let s = format!("reports.day.annotations:\n{}", INPUT);
+        let twinput = TimeWarriorInput::parse_from_str(&s).unwrap();
+        assert_eq!(twinput.get_config("reports.day.annotations"), Some(""));
+    }
+
+    #[test]
+    fn parse_from_str_invalid_interval() {
+        let s = INPUT.replace("\"end\":\"20210722T153753Z\"", "\"end\":1");
+        assert!(TimeWarriorInput::parse_from_str(&s)
+            .err()
+            .unwrap()
+            .starts_with("Invalid interval, the end isn't a time"));
+    }
+
+    #[test]
+    fn parse_from_str_without_range() {
+        assert!(TimeWarriorInput::parse_from_str("aggregate.week.layout: iso\n").is_err());
+    }
 }
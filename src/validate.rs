@@ -0,0 +1,152 @@
+// Checks the allocation files without running a report, so that typos are found before they get
+// in the way of a report.
+
+use crate::workgroup::{self, WorkGroup};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Returns the problems found in the contents of an allocation file.
+pub fn validate_allocation(contents: &str) -> Vec<String> {
+    let parsed_json = match json::parse(contents) {
+        Ok(val) => val,
+        Err(err) => return vec![format!("malformed json: {}", err)],
+    };
+    if !parsed_json.is_array() {
+        return vec!["the file is not a list of groups".to_string()];
+    }
+    if parsed_json.is_empty() {
+        return vec!["the file has no groups".to_string()];
+    }
+
+    let mut problems = Vec::new();
+    let mut workgroups: Vec<(usize, WorkGroup)> = Vec::new();
+    for (index, jv) in parsed_json.members().enumerate() {
+        let group_problems = WorkGroup::check_json_value(jv);
+        if !group_problems.is_empty() {
+            for problem in group_problems {
+                problems.push(format!("group {}: {}", index + 1, problem));
+            }
+            continue;
+        }
+        // Reports accept a group with empty tags, but it takes every interval.
+        if jv["tags"].is_empty() {
+            problems.push(format!(
+                "group {}: 'tags' is empty, so the group matches every interval",
+                index + 1
+            ));
+            continue;
+        }
+        workgroups.push((index + 1, WorkGroup::parse_from_json_value(jv)));
+    }
+
+    // An entry is counted towards the first group whose tags it has, so a group is never reached
+    // if an earlier group has a subset of its tags.
+    for (position, (index, workgroup)) in workgroups.iter().enumerate() {
+        let earlier = workgroups[..position]
+            .iter()
+            .find(|(_, other)| other.tagset.has_all_tags_of(&workgroup.tagset));
+        if let Some((other_index, other)) = earlier {
+            if other.tagset.tags == workgroup.tagset.tags {
+                problems.push(format!(
+                    "group {}: '{}' is a duplicate of group {}",
+                    index,
+                    workgroup.name(),
+                    other_index
+                ));
+            } else {
+                problems.push(format!(
+                    "group {}: '{}' is unreachable since every entry it matches is counted towards group {} '{}'",
+                    index,
+                    workgroup.name(),
+                    other_index,
+                    other.name()
+                ));
+            }
+        }
+    }
+    problems
+}
+
+// Validates every allocation file below the given directory and returns the files with problems.
+pub fn validate_all(dir: &Path) -> (usize, Vec<(PathBuf, Vec<String>)>) {
+    let files = workgroup::allocation_files(dir);
+    let mut results = Vec::new();
+    for path in files.iter() {
+        let problems = match fs::read_to_string(path) {
+            Ok(contents) => validate_allocation(&contents),
+            Err(err) => vec![format!("unable to read the file: {}", err)],
+        };
+        if !problems.is_empty() {
+            results.push((path.clone(), problems));
+        }
+    }
+    (files.len(), results)
+}
+
+// Returns the count followed by the noun, in the plural unless the count is one.
+fn count_of(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        _ => format!("{} {}s", count, noun),
+    }
+}
+
+// Prints the problems and returns whether all the files are valid.
+pub fn print_validation(count: usize, results: &[(PathBuf, Vec<String>)]) -> bool {
+    let mut problem_count = 0;
+    for (path, problems) in results {
+        for problem in problems {
+            println!("{}: {}", path.display(), problem);
+            problem_count += 1;
+        }
+    }
+    println!(
+        "Checked {}, found {}.",
+        count_of(count, "allocation file"),
+        count_of(problem_count, "problem")
+    );
+    problem_count == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{count_of, validate_allocation};
+
+    #[test]
+    fn validate_allocation_valid() {
+        let s = "[{\"tags\": [\"office\", \"project\"], \"allocation\": 2.5}, {\"tags\": [\"office\"], \"allocation\": 1}]";
+        assert!(validate_allocation(s).is_empty());
+        assert_eq!(count_of(1, "problem"), "1 problem");
+        assert_eq!(count_of(0, "problem"), "0 problems");
+    }
+
+    #[test]
+    fn validate_allocation_malformed() {
+        assert!(validate_allocation("[{\"tags\": [\"office\"],")[0].starts_with("malformed json"));
+        assert_eq!(
+            validate_allocation("{}"),
+            vec!["the file is not a list of groups"]
+        );
+        assert_eq!(validate_allocation("[]"), vec!["the file has no groups"]);
+    }
+
+    #[test]
+    fn validate_allocation_groups() {
+        let s = "[
+            {\"tags\": [\"office\"], \"allocation\": 1},
+            {\"tags\": [], \"allocation\": 1},
+            {\"tags\": [\"review\"], \"allocation\": \"1\"},
+            {\"tags\": [\"office\", \"project\"], \"allocation\": 2},
+            {\"tags\": [\"office\"], \"allocation\": 3}
+        ]";
+        assert_eq!(
+            validate_allocation(s),
+            vec![
+                "group 2: 'tags' is empty, so the group matches every interval",
+                "group 3: 'allocation' \"1\" is not a number",
+                "group 4: 'office project' is unreachable since every entry it matches is counted towards group 1 'office'",
+                "group 5: 'office' is a duplicate of group 1",
+            ]
+        );
+    }
+}
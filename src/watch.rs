@@ -0,0 +1,121 @@
+// Keeps the report of a range up to date in the terminal. The timewarrior data directory, its
+// configuration and the allocation files are checked every second and the report is redrawn in
+// place when any of them changes, and on a timer so that the interval still going on keeps growing.
+
+use crate::report::{self, Report};
+use crate::standalone;
+use crate::status;
+use crate::style::Style;
+use crate::twdb;
+use crate::workgroup;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+static USAGE: &str =
+    "Usage: aggregate watch [<range>] [--interval <seconds>] [rc.<name>=<value> ...]\n";
+
+// Returns the seconds between redraws, a minute unless given, and the rest of the arguments.
+pub fn parse_args(args: &[String]) -> Result<(u64, Vec<String>), String> {
+    let mut interval = 60;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg != "--interval" {
+            rest.push(arg.clone());
+            continue;
+        }
+        interval = match args.next().map(|val| val.parse::<u64>()) {
+            Some(Ok(val)) if val > 0 => val,
+            _ => return Err(USAGE.to_string()),
+        };
+    }
+    Ok((interval, rest))
+}
+
+// Returns the modification times of the files the report is made from.
+fn snapshot(db_dir: &Path, config_dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut paths = vec![db_dir.join("timewarrior.cfg")];
+    if let Ok(entries) = fs::read_dir(db_dir.join("data")) {
+        paths.extend(entries.flatten().map(|entry| entry.path()));
+    }
+    paths.extend(workgroup::allocation_files(config_dir));
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let modified = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            (path, modified)
+        })
+        .collect()
+}
+
+// Returns the lines of the report, or of the error which keeps it from being made.
+fn render(args: &[String]) -> Vec<String> {
+    let twinput = match standalone::load_input(args) {
+        Ok(val) => val,
+        Err(err) => return err.lines().map(|line| line.to_string()).collect(),
+    };
+    let mut workgroups = match workgroup::get_workgroups(&twinput) {
+        Ok(val) => val,
+        Err(err) => return err.lines().map(|line| line.to_string()).collect(),
+    };
+    workgroup::process(&twinput, &mut workgroups);
+    let style = match Style::for_terminal(&twinput.config) {
+        Ok(val) => val,
+        Err(err) => return err.lines().map(|line| line.to_string()).collect(),
+    };
+    let mut report = Report::from_workgroups(None, &workgroups);
+    report.active = status::active_group(&twinput, &workgroups);
+    report::render(&[report], &style)
+}
+
+// Redraws the report until interrupted.
+pub fn watch(args: &[String]) -> Result<(), String> {
+    let (interval, args) = parse_args(args)?;
+    // Fail early on arguments which would keep every redraw from working.
+    standalone::load_input(&args)?;
+
+    let db_dir = twdb::db_dir();
+    let config_dir = PathBuf::from(workgroup::config_dir());
+    let interval = Duration::from_secs(interval);
+    let mut last_snapshot = None;
+    let mut last_draw = Instant::now();
+    loop {
+        let current = snapshot(&db_dir, &config_dir);
+        if last_snapshot.as_ref() != Some(&current) || last_draw.elapsed() >= interval {
+            println!("\x1b[2J\x1b[H{}", render(&args).join("\n"));
+            io::stdout().flush().unwrap();
+            last_snapshot = Some(current);
+            last_draw = Instant::now();
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_args;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_args_interval() {
+        let (interval, rest) = parse_args(&args(&[":week", "rc.color=off"])).unwrap();
+        assert_eq!(interval, 60);
+        assert_eq!(rest, args(&[":week", "rc.color=off"]));
+
+        let (interval, rest) = parse_args(&args(&["--interval", "5", ":day"])).unwrap();
+        assert_eq!(interval, 5);
+        assert_eq!(rest, args(&[":day"]));
+
+        assert!(parse_args(&args(&["--interval"])).is_err());
+        assert!(parse_args(&args(&["--interval", "0"])).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+// Helpers to work with weeks which start on a configurable day. Timewarrior treats Monday as the
+// start of the week unless configured otherwise, and this extension follows it unless
+// aggregate.week.start says otherwise.
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::HashMap;
+
+// The settings of timewarrior for the start of the week, in order of precedence.
+static TIMEWARRIOR_KEYS: [&str; 2] = ["reports.week.start", "weekstart"];
+
+static DAYS_FROM_SUNDAY: [Weekday; 7] = [
+    Weekday::Sun,
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+];
+
+fn parse_day(value: &str) -> Option<Weekday> {
+    match value.to_lowercase().as_str() {
+        "monday" | "mon" => Some(Weekday::Mon),
+        "tuesday" | "tue" => Some(Weekday::Tue),
+        "wednesday" | "wed" => Some(Weekday::Wed),
+        "thursday" | "thu" => Some(Weekday::Thu),
+        "friday" | "fri" => Some(Weekday::Fri),
+        "saturday" | "sat" => Some(Weekday::Sat),
+        "sunday" | "sun" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+pub fn parse_week_start(value: Option<&str>) -> Result<Weekday, String> {
+    let value = match value {
+        Some(val) => val,
+        None => return Ok(Weekday::Mon),
+    };
+    match parse_day(value) {
+        Some(val) => Ok(val),
+        None => Err(format!(
+            "Unsupported value '{}' for aggregate.week.start. Use the name of a day like 'sunday'.\n",
+            value
+        )),
+    }
+}
+
+// Returns the first day of the week set with aggregate.week.start, or else with timewarrior's own
+// week start setting, which is also what timewarrior's :week ranges follow.
+pub fn week_start_from_config(config: &HashMap<String, String>) -> Result<Weekday, String> {
+    if let Some(value) = config.get("aggregate.week.start") {
+        return parse_week_start(Some(value));
+    }
+    for key in TIMEWARRIOR_KEYS.iter() {
+        let value = match config.get(*key) {
+            Some(val) => val,
+            None => continue,
+        };
+        // Timewarrior also takes the number of the day, counting from Sunday as 0.
+        return match (value.parse::<usize>(), parse_day(value)) {
+            (Ok(number), _) if number < DAYS_FROM_SUNDAY.len() => Ok(DAYS_FROM_SUNDAY[number]),
+            (_, Some(day)) => Ok(day),
+            _ => Err(format!(
+                "Unsupported value '{}' for {}. Use the name of a day like 'sunday'.\n",
+                value, key
+            )),
+        };
+    }
+    Ok(Weekday::Mon)
+}
+
+// Returns the first day of the week which contains the given day.
+pub fn week_start_of(day: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let offset = (day.weekday().num_days_from_monday() + 7 - week_start.num_days_from_monday()) % 7;
+    day - Duration::days(offset as i64)
+}
+
+// Returns the ISO (year, week) the week starting on the given day is named after. It is the ISO week
+// of the middle of the week, which for weeks starting on Monday is the ISO week of every day in it.
+pub fn iso_week_of(week_start: NaiveDate) -> (i32, u32) {
+    let (year, week, _) = (week_start + Duration::days(3)).isoweekdate();
+    (year, week)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{iso_week_of, parse_week_start, week_start_from_config, week_start_of};
+    use chrono::{NaiveDate, Weekday};
+    use std::collections::HashMap;
+
+    #[test]
+    fn parse_week_start_values() {
+        assert_eq!(parse_week_start(None), Ok(Weekday::Mon));
+        assert_eq!(parse_week_start(Some("Sunday")), Ok(Weekday::Sun));
+        assert_eq!(parse_week_start(Some("sat")), Ok(Weekday::Sat));
+        assert!(parse_week_start(Some("someday")).is_err());
+    }
+
+    #[test]
+    fn week_start_from_timewarrior() {
+        let mut config = HashMap::new();
+        assert_eq!(week_start_from_config(&config), Ok(Weekday::Mon));
+        config.insert("weekstart".to_string(), "Sunday".to_string());
+        assert_eq!(week_start_from_config(&config), Ok(Weekday::Sun));
+        config.insert("weekstart".to_string(), "1".to_string());
+        assert_eq!(week_start_from_config(&config), Ok(Weekday::Mon));
+        config.insert("weekstart".to_string(), "0".to_string());
+        assert_eq!(week_start_from_config(&config), Ok(Weekday::Sun));
+        config.insert("weekstart".to_string(), "6".to_string());
+        assert_eq!(week_start_from_config(&config), Ok(Weekday::Sat));
+        config.insert("reports.week.start".to_string(), "saturday".to_string());
+        assert_eq!(week_start_from_config(&config), Ok(Weekday::Sat));
+        // The setting of the extension takes precedence.
+        config.insert("aggregate.week.start".to_string(), "tue".to_string());
+        assert_eq!(week_start_from_config(&config), Ok(Weekday::Tue));
+
+        let mut config = HashMap::new();
+        config.insert("weekstart".to_string(), "someday".to_string());
+        assert!(week_start_from_config(&config).is_err());
+        config.insert("weekstart".to_string(), "7".to_string());
+        assert!(week_start_from_config(&config).is_err());
+    }
+
+    #[test]
+    fn week_start_of_monday_weeks() {
+        let day = NaiveDate::from_ymd(2021, 8, 1);
+        assert_eq!(
+            week_start_of(day, Weekday::Mon),
+            NaiveDate::from_ymd(2021, 7, 26)
+        );
+        let day = NaiveDate::from_ymd(2021, 7, 26);
+        assert_eq!(
+            week_start_of(day, Weekday::Mon),
+            NaiveDate::from_ymd(2021, 7, 26)
+        );
+    }
+
+    #[test]
+    fn week_start_of_sunday_weeks() {
+        let day = NaiveDate::from_ymd(2021, 8, 1);
+        assert_eq!(
+            week_start_of(day, Weekday::Sun),
+            NaiveDate::from_ymd(2021, 8, 1)
+        );
+        let day = NaiveDate::from_ymd(2021, 7, 29);
+        assert_eq!(
+            week_start_of(day, Weekday::Sun),
+            NaiveDate::from_ymd(2021, 7, 25)
+        );
+    }
+
+    #[test]
+    fn iso_week_of_week_starts() {
+        assert_eq!(iso_week_of(NaiveDate::from_ymd(2021, 7, 26)), (2021, 30));
+        // A week from Sunday to Saturday is named after the ISO week its weekdays belong to.
+        assert_eq!(iso_week_of(NaiveDate::from_ymd(2021, 7, 25)), (2021, 30));
+        assert_eq!(iso_week_of(NaiveDate::from_ymd(2020, 12, 28)), (2020, 53));
+    }
+}
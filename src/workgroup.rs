@@ -1,50 +1,73 @@
 use crate::tagset::TagSet;
 use chrono::Duration;
-use chrono::{Datelike, Local, NaiveDateTime, TimeZone};
-use json::JsonValue;
+use chrono::{Datelike, NaiveDate};
+use json::{object, JsonValue};
 use std::env;
+use std::ffi::OsStr;
 use std::fmt;
 use std::fs;
 use std::ops::Add;
+use std::path::{Path, PathBuf};
 
 use log::debug;
 
+use crate::hook::{self, Hook};
+use crate::report::ReportRow;
+use crate::style::Style;
+use crate::timezone;
 use crate::twentry::TimeWarriorEntry;
 use crate::twinput::TimeWarriorInput;
+use crate::week;
 
-static SAMPLE: &'static str = "
-[
-    {
-        \"tags\": [
-            \"office\",
-            \"project\"
-        ],
-        \"allocation\": 3
-    },
-    {
-        \"tags\": [
-            \"office\",
-            \"maintenance\"
-        ],
-        \"allocation\": 3
-    },
-    {
-        \"tags\": [
-            \"office\",
-            \"review\"
-        ],
-        \"allocation\": 1
-    }
-]
-";
-
+#[derive(Clone)]
 pub struct WorkGroup {
     pub tagset: TagSet,
     pub time_allocated: Duration,
     pub time_spent: Duration,
+    // The commands run when the group reaches a percentage of its allocation.
+    pub hooks: Vec<Hook>,
 }
 
 impl WorkGroup {
+    pub fn new(tagset: TagSet, time_allocated: Duration) -> Self {
+        WorkGroup {
+            tagset,
+            time_allocated,
+            time_spent: Duration::seconds(0),
+            hooks: Vec::new(),
+        }
+    }
+
+    // Returns the problems which prevent the group from being parsed. A group with empty tags is
+    // fine, it matches every interval.
+    pub fn check_json_value(jv: &JsonValue) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !jv.is_object() {
+            problems.push(format!("the group {} is not an object", jv.dump()));
+            return problems;
+        }
+        if !jv["tags"].is_array() {
+            problems.push("'tags' is missing or is not a list".to_string());
+        } else if jv["tags"].members().any(|tag| !tag.is_string()) {
+            problems.push("'tags' has a tag which is not a string".to_string());
+        }
+        if jv["allocation"].is_null() {
+            problems.push("'allocation' is missing".to_string());
+        } else if !jv["allocation"].is_number() {
+            problems.push(format!(
+                "'allocation' {} is not a number",
+                jv["allocation"].dump()
+            ));
+        } else if jv["allocation"].as_f64().unwrap() < 0.0 {
+            problems.push(format!(
+                "'allocation' {} is negative",
+                jv["allocation"].dump()
+            ));
+        }
+        problems.extend(hook::check_json_value(&jv["hooks"]));
+        problems
+    }
+
     pub fn parse_from_json_value(jv: &JsonValue) -> Self {
         // Get the tags from the entry.
         let tagset = TagSet::parse_from_json_value(jv);
@@ -59,7 +82,26 @@ impl WorkGroup {
             tagset,
             time_spent,
             time_allocated,
+            hooks: hook::parse_from_json_value(&jv["hooks"]),
+        }
+    }
+
+    // Returns the group as it is written in the allocation files, with the allocation in hours.
+    pub fn to_json_value(&self) -> JsonValue {
+        let allocation = self.time_allocated.num_seconds() as f64 / 3600.0;
+        let mut jv = object! {
+            tags: self.tagset.sorted_tags(),
+            allocation: allocation,
+        };
+        if !self.hooks.is_empty() {
+            jv["hooks"] = hook::to_json_value(&self.hooks);
         }
+        jv
+    }
+
+    // Returns the name of the group made of its sorted tags, like 'office project'.
+    pub fn name(&self) -> String {
+        self.tagset.sorted_tags().join(" ")
     }
 
     pub fn matches(&self, twe: &TimeWarriorEntry) -> bool {
@@ -71,109 +113,153 @@ impl WorkGroup {
     }
 }
 
-fn format_duration(duration: Duration) -> String {
-    let hours = duration.num_hours();
-    let minutes = duration.num_minutes() % 60;
-    format!("{} hrs {} mins", hours, minutes)
-}
-
 impl fmt::Display for WorkGroup {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut tags_sorted = Vec::new();
-        for tag in self.tagset.tags.iter() {
-            tags_sorted.push(tag.clone());
+        let cells: Vec<String> = ReportRow::from_workgroup(self)
+            .cells(&Style::plain())
+            .into_iter()
+            .map(|cell| cell.text)
+            .collect();
+        write!(f, "| {}", cells.join(" | "))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeekLayout {
+    // <year>/<month>/week-of-<day>.json named after the first day of the week.
+    Month,
+    // <iso year>/W<iso week>.json which doesn't depend on the month the week starts in.
+    IsoWeek,
+}
+
+impl WeekLayout {
+    pub fn parse_from_config(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("month") => Ok(WeekLayout::Month),
+            Some("iso") => Ok(WeekLayout::IsoWeek),
+            Some(other) => Err(format!(
+                "Unsupported value '{}' for aggregate.week.layout. Supported values are 'month' and 'iso'.\n",
+                other
+            )),
         }
-        tags_sorted.sort();
-        let spent = format_duration(self.time_spent);
-        let allocated = format_duration(self.time_allocated);
-        let remaining = match self.time_allocated.checked_sub(&self.time_spent) {
-            Some(val) => format_duration(val),
-            None => format_duration(chrono::Duration::seconds(0)),
-        };
-        write!(
-            f,
-            "| {0: <20} | {1: <15} | {2: <15} | {3: <15}",
-            tags_sorted.join(" "),
-            spent,
-            allocated,
-            remaining
-        )
     }
 }
 
-pub fn get_workgroups(twinput: &TimeWarriorInput) -> Result<Vec<WorkGroup>, String> {
-    // let config_dir = env::var("HOME").unwrap() + "/.timewarrior/aggregate";
-    // //let workgroups = Vec::new();
-    let start = NaiveDateTime::parse_from_str(&twinput.start, "%Y%m%dT%H%M%SZ").unwrap();
-    let end = NaiveDateTime::parse_from_str(&twinput.end, "%Y%m%dT%H%M%SZ").unwrap();
-    let duration = end.signed_duration_since(start);
-
-    let config_dir = env::var("HOME").unwrap() + "/.timewarrior/aggregate";
-    let start = Local.from_utc_datetime(&start).date().naive_local();
-    let end = Local.from_utc_datetime(&end).date().naive_local();
-    let year = start.year();
-    let month = start.month();
-    let day = start.day();
-
-    let allocation_file_path;
-    let allocation_file_contents = match duration.num_days() {
-        1 => {
-            allocation_file_path =
-                format!("{}/allocation/{}/{}/{}.json", config_dir, year, month, day);
-            debug!(
-                "Looking for workgroups definition at {}",
-                &allocation_file_path
-            );
-            match fs::read_to_string(&allocation_file_path) {
-                Ok(val) => val,
-                Err(_) => match env::var("SAMPLE") {
-                    Ok(_) => {
-                        return Err(SAMPLE.to_string());
-                    }
-                    Err(_) => {
-                        return Err(format!(
-                        "Unable to open the workgroups definition file for the day {} at {}.\nRerun the same command with SAMPLE=1 for a sample json file.",
-                        start, allocation_file_path
-                    ));
-                    }
-                },
-            }
+// Returns the directory holding the allocation files and everything else of this extension.
+pub fn config_dir() -> String {
+    env::var("HOME").unwrap() + "/.timewarrior/aggregate"
+}
+
+pub fn day_allocation_file_path(config_dir: &str, day: NaiveDate) -> String {
+    format!(
+        "{}/allocation/{}/{}/{}.json",
+        config_dir,
+        day.year(),
+        day.month(),
+        day.day()
+    )
+}
+
+// Returns the candidate paths of the allocation file for the week starting on the given day. The
+// path of the preferred layout comes first and the other layout is used as a fallback, so that
+// existing files keep working after switching the layout.
+pub fn week_allocation_file_paths(
+    config_dir: &str,
+    week_start: NaiveDate,
+    layout: WeekLayout,
+) -> Vec<String> {
+    let month_path = format!(
+        "{}/allocation/{}/{}/week-of-{}.json",
+        config_dir,
+        week_start.year(),
+        week_start.month(),
+        week_start.day()
+    );
+    let (iso_year, iso_week) = week::iso_week_of(week_start);
+    let iso_path = format!(
+        "{}/allocation/{}/W{:02}.json",
+        config_dir, iso_year, iso_week
+    );
+    match layout {
+        WeekLayout::Month => vec![month_path, iso_path],
+        WeekLayout::IsoWeek => vec![iso_path, month_path],
+    }
+}
+
+// Reads the first of the given files which exists and returns its path along with the contents.
+pub fn read_first_existing(paths: &[String]) -> Option<(String, String)> {
+    for path in paths {
+        debug!("Looking for workgroups definition at {}", path);
+        if let Ok(contents) = fs::read_to_string(path) {
+            return Some((path.clone(), contents));
         }
+    }
+    None
+}
+
+// Returns the candidate paths of the allocation file for the range of the input, the preferred one
+// first, along with a description of the range.
+pub fn allocation_file_paths(twinput: &TimeWarriorInput) -> Result<(Vec<String>, String), String> {
+    let (start, end, days) = timezone::local_range(&twinput.start, &twinput.end)?;
+    let config_dir = config_dir();
+
+    match days {
+        1 => Ok((
+            vec![day_allocation_file_path(&config_dir, start)],
+            format!("the day {}", start),
+        )),
         7 => {
-            allocation_file_path = format!(
-                "{}/allocation/{}/{}/week-of-{}.json",
-                config_dir, year, month, day
-            );
-            debug!(
-                "Looking for workgroups definition at {}",
-                &allocation_file_path
-            );
-            match fs::read_to_string(&allocation_file_path) {
-                Ok(val) => val,
-                Err(_) => match env::var("SAMPLE") {
-                    Ok(_) => {
-                        return Err(SAMPLE.to_string());
-                    }
-                    Err(_) => {
-                        return Err(format!(
-                        "Unable to open the workgroups definition file for the week starting on {} at {}.\nRerun the same command with SAMPLE=1 for a sample json file.",
-                        start, allocation_file_path
-                    ));
-                    }
-                },
+            let layout =
+                WeekLayout::parse_from_config(twinput.get_config("aggregate.week.layout"))?;
+            // A week which starts on another day would share its file with the week it starts in.
+            let week_start = week::week_start_from_config(&twinput.config)?;
+            let first_day = week::week_start_of(start, week_start);
+            if first_day != start {
+                return Err(format!(
+                    "The range from {} to {} doesn't start on the first day of the week, which is a {}. Set aggregate.week.start to report on weeks starting on another day.\n",
+                    start,
+                    end,
+                    first_day.format("%A")
+                ));
             }
+            Ok((
+                week_allocation_file_paths(&config_dir, start, layout),
+                format!("the week starting on {}", start),
+            ))
         }
-        _ => {
+        _ => Err(format!(
+            "Unsupported duration of {} days. Start = {}, End = {}. Only range of one day or one week is supported",
+            days,
+            start,
+            end
+        )),
+    }
+}
+
+pub fn get_workgroups(twinput: &TimeWarriorInput) -> Result<Vec<WorkGroup>, String> {
+    let (allocation_file_paths, description) = allocation_file_paths(twinput)?;
+    let (allocation_file_path, allocation_file_contents) = match read_first_existing(
+        &allocation_file_paths,
+    ) {
+        Some(val) => val,
+        None => {
             return Err(format!(
-                "Unsupported duration of {} days. Start = {}, End = {}. Only range of one day or one week is supported",
-                duration.num_days(),
-                start,
-                end
-            ));
+                    "Unable to open the workgroups definition file for {} at {}.\nRerun the same command with SAMPLE=1 for a sample json file.",
+                    description,
+                    allocation_file_paths.join(" or ")
+                ));
         }
     };
 
-    let parsed_json = match json::parse(&allocation_file_contents) {
+    parse_allocation(&allocation_file_path, &allocation_file_contents)
+}
+
+// Parses the contents of the allocation file at the given path.
+pub fn parse_allocation(
+    allocation_file_path: &str,
+    allocation_file_contents: &str,
+) -> Result<Vec<WorkGroup>, String> {
+    let parsed_json = match json::parse(allocation_file_contents) {
         Ok(val) => val,
         Err(err) => {
             return Err(format!(
@@ -184,10 +270,19 @@ pub fn get_workgroups(twinput: &TimeWarriorInput) -> Result<Vec<WorkGroup>, Stri
     };
 
     let mut workgroups = Vec::new();
-    for jv in parsed_json.members() {
+    for (index, jv) in parsed_json.members().enumerate() {
+        let problems = WorkGroup::check_json_value(jv);
+        if !problems.is_empty() {
+            return Err(format!(
+                "Invalid group {} in the json file at {}: {}\nRun 'aggregate validate' to check all the allocation files.\n",
+                index + 1,
+                allocation_file_path,
+                problems.join(", ")
+            ));
+        }
         workgroups.push(WorkGroup::parse_from_json_value(jv));
     }
-    if workgroups.len() == 0 {
+    if workgroups.is_empty() {
         return Err(format!(
             "No workgroups found in the json file at {}",
             allocation_file_path,
@@ -197,51 +292,105 @@ pub fn get_workgroups(twinput: &TimeWarriorInput) -> Result<Vec<WorkGroup>, Stri
     Ok(workgroups)
 }
 
-pub fn process(twinput: &TimeWarriorInput, workgroups: &mut Vec<WorkGroup>) {
-    for twentry in twinput.twentries.iter() {
+// Returns the workgroups in the format of the allocation files.
+pub fn to_allocation_json(workgroups: &[WorkGroup]) -> String {
+    let mut jv = JsonValue::new_array();
+    for workgroup in workgroups {
+        jv.push(workgroup.to_json_value()).unwrap();
+    }
+    jv.pretty(4)
+}
+
+// Returns the contents of an allocation file with the allocations of its groups replaced by the
+// ones of the workgroups parsed from it, so that anything else in the file, like keys aggregate
+// doesn't know about, is kept. The file is only written from scratch when it can't be matched up.
+pub fn with_allocations(contents: &str, workgroups: &[WorkGroup]) -> String {
+    let mut jv = match json::parse(contents) {
+        Ok(val) if val.is_array() && val.len() == workgroups.len() => val,
+        _ => return to_allocation_json(workgroups),
+    };
+    for (index, workgroup) in workgroups.iter().enumerate() {
+        jv[index]["allocation"] = workgroup.to_json_value()["allocation"].take();
+    }
+    jv.pretty(4)
+}
+
+pub fn process(twinput: &TimeWarriorInput, workgroups: &mut [WorkGroup]) {
+    process_entries(&twinput.twentries, workgroups);
+}
+
+// Adds the time of every entry to the first workgroup it matches.
+pub fn process_entries(twentries: &[TimeWarriorEntry], workgroups: &mut [WorkGroup]) {
+    for twentry in twentries.iter() {
         for workgroup in workgroups.iter_mut() {
-            if workgroup.matches(&twentry) {
-                workgroup.process(&twentry);
+            if workgroup.matches(twentry) {
+                workgroup.process(twentry);
                 break;
             }
         }
     }
 }
 
-pub fn print_result(workgroups: &Vec<WorkGroup>) {
-    println!(
-        "| {0: <20} | {1: <15} | {2: <15} | {3: <15}",
-        "group", "spent", "allocated", "remaining"
-    );
-    let mut total_spent = Duration::seconds(0);
-    let mut total_allocated = Duration::seconds(0);
-    for workgroup in workgroups {
-        println!("{}", workgroup);
-        total_spent = total_spent.add(workgroup.time_spent);
-        total_allocated = total_allocated.add(workgroup.time_allocated);
+// Writes the plan to the preferred one of the candidate paths of its allocation file, creating its
+// directory if needed, and returns that path. Unless forced, nothing is written when any of the
+// candidates exists, so that a plan is neither lost nor shadowed by accident.
+pub fn write_allocation_file(
+    paths: &[String],
+    contents: &str,
+    force: bool,
+) -> Result<String, String> {
+    if !force {
+        if let Some((existing, _)) = read_first_existing(paths) {
+            return Err(format!(
+                "The allocation file {} already exists, use --force to replace it.\n",
+                existing
+            ));
+        }
     }
+    let path = Path::new(&paths[0]);
+    let result = fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(path, contents));
+    match result {
+        Ok(_) => Ok(paths[0].clone()),
+        Err(err) => Err(format!(
+            "Unable to write the allocation file {}\nError: '{}'\n",
+            path.display(),
+            err
+        )),
+    }
+}
 
-    let total_remaining = match total_allocated.checked_sub(&total_spent) {
-        Some(val) => val,
-        None => chrono::Duration::seconds(0),
-    };
-    println!(
-        "| {0: <20} | {1: <15} | {2: <15} | {3: <15}",
-        "total",
-        format_duration(total_spent),
-        format_duration(total_allocated),
-        format_duration(total_remaining),
-    );
+// Returns the allocation files found below the given directory, sorted by their path.
+pub fn allocation_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.append(&mut allocation_files(&path));
+            } else if path.extension() == Some(OsStr::new("json")) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
 }
 
 #[cfg(test)]
 mod test {
-    use super::WorkGroup;
+    use super::{
+        allocation_file_paths, parse_allocation, process_entries, week_allocation_file_paths,
+        with_allocations, WeekLayout, WorkGroup,
+    };
+    use crate::clock::Clock;
+    use crate::testutil;
+    use crate::timezone;
+    use chrono::{Duration, NaiveDate};
 
     #[test]
     fn parse_from_json_value() {
         let s = "{\"tags\": [ \"office\", \"project\" ], \"allocation\": 0.5}";
-        let jv = json::parse(&s).unwrap();
+        let jv = json::parse(s).unwrap();
         let wg = WorkGroup::parse_from_json_value(&jv);
 
         assert_eq!(wg.tagset.tags.len(), 2);
@@ -255,29 +404,29 @@ mod test {
     #[test]
     fn matches() {
         let s = "{\"tags\": [ \"personal\", \"learning\" ], \"allocation\": 0.5}";
-        let jv = json::parse(&s).unwrap();
+        let jv = json::parse(s).unwrap();
         let wg = WorkGroup::parse_from_json_value(&jv);
 
         let s = "{\"id\":3,\"start\":\"20210722T152328Z\",\"tags\":[\"Rust talks\",\"learning\",\"personal\"]}";
-        let jv = json::parse(&s).unwrap();
-        let twe = super::TimeWarriorEntry::parse_from_json_value(&jv);
+        let jv = json::parse(s).unwrap();
+        let twe = super::TimeWarriorEntry::parse_from_json_value(&jv, &Clock::system()).unwrap();
         assert!(wg.matches(&twe));
 
         let s = "{\"id\":3,\"start\":\"20210722T152328Z\",\"tags\":[\"Rust talks\",\"office\",\"project\"]}";
-        let jv = json::parse(&s).unwrap();
-        let twe = super::TimeWarriorEntry::parse_from_json_value(&jv);
+        let jv = json::parse(s).unwrap();
+        let twe = super::TimeWarriorEntry::parse_from_json_value(&jv, &Clock::system()).unwrap();
         assert!(!wg.matches(&twe));
     }
 
     #[test]
     fn duration() {
         let s = "{\"tags\": [ \"personal\", \"learning\" ], \"allocation\": 0.5}";
-        let jv = json::parse(&s).unwrap();
+        let jv = json::parse(s).unwrap();
         let mut wg = WorkGroup::parse_from_json_value(&jv);
 
         let s = "{\"id\":3,\"start\":\"20210722T152328Z\",\"end\":\"20210722T152330Z\",\"tags\":[\"Rust talks\",\"learning\",\"personal\"]}";
-        let jv = json::parse(&s).unwrap();
-        let twe = super::TimeWarriorEntry::parse_from_json_value(&jv);
+        let jv = json::parse(s).unwrap();
+        let twe = super::TimeWarriorEntry::parse_from_json_value(&jv, &Clock::system()).unwrap();
 
         assert_eq!(wg.time_spent.num_seconds(), 0);
         wg.process(&twe);
@@ -285,4 +434,156 @@ mod test {
         wg.process(&twe);
         assert_eq!(wg.time_spent.num_seconds(), 4);
     }
+
+    #[test]
+    fn week_allocation_file_paths_month_layout() {
+        let week_start = NaiveDate::from_ymd(2021, 7, 26);
+        let paths = week_allocation_file_paths("/aggregate", week_start, WeekLayout::Month);
+        assert_eq!(
+            paths,
+            vec![
+                "/aggregate/allocation/2021/7/week-of-26.json",
+                "/aggregate/allocation/2021/W30.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn allocation_file_paths_week_start() {
+        let _lock = timezone::lock_timezone("UTC");
+
+        let twinput = testutil::input_for_range(
+            NaiveDate::from_ymd(2021, 7, 26),
+            NaiveDate::from_ymd(2021, 8, 2),
+            &[],
+        );
+        let (paths, description) = allocation_file_paths(&twinput).unwrap();
+        assert!(paths[0].ends_with("/allocation/2021/7/week-of-26.json"));
+        assert_eq!(description, "the week starting on 2021-07-26");
+
+        // A week from Tuesday would otherwise use the file of the week starting on Monday.
+        let twinput = testutil::input_for_range(
+            NaiveDate::from_ymd(2021, 7, 27),
+            NaiveDate::from_ymd(2021, 8, 3),
+            &[],
+        );
+        let err = allocation_file_paths(&twinput).unwrap_err();
+        assert!(err.contains("which is a Monday"));
+    }
+
+    #[test]
+    fn week_allocation_file_paths_iso_layout() {
+        // The week starting on the last day of 2018 is the first week of the ISO year 2019.
+        let week_start = NaiveDate::from_ymd(2018, 12, 31);
+        let paths = week_allocation_file_paths("/aggregate", week_start, WeekLayout::IsoWeek);
+        assert_eq!(
+            paths,
+            vec![
+                "/aggregate/allocation/2019/W01.json",
+                "/aggregate/allocation/2018/12/week-of-31.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn week_layout_parse_from_config() {
+        assert_eq!(WeekLayout::parse_from_config(None), Ok(WeekLayout::Month));
+        assert_eq!(
+            WeekLayout::parse_from_config(Some("month")),
+            Ok(WeekLayout::Month)
+        );
+        assert_eq!(
+            WeekLayout::parse_from_config(Some("iso")),
+            Ok(WeekLayout::IsoWeek)
+        );
+        assert!(WeekLayout::parse_from_config(Some("yearly")).is_err());
+    }
+
+    #[test]
+    fn to_json_value() {
+        let s = "{\"tags\": [ \"office\", \"project\" ], \"allocation\": 2.5}";
+        let jv = json::parse(s).unwrap();
+        let wg = WorkGroup::parse_from_json_value(&jv);
+        assert_eq!(
+            wg.to_json_value().dump(),
+            "{\"tags\":[\"office\",\"project\"],\"allocation\":2.5}"
+        );
+
+        let s = "{\"tags\": [\"office\"], \"allocation\": 1, \"hooks\": {\"100\": \"timew stop\"}}";
+        let wg = WorkGroup::parse_from_json_value(&json::parse(s).unwrap());
+        assert_eq!(
+            wg.to_json_value().dump(),
+            "{\"tags\":[\"office\"],\"allocation\":1,\"hooks\":{\"100\":\"timew stop\"}}"
+        );
+    }
+
+    #[test]
+    fn process_entries_first_match() {
+        let s = "[{\"tags\": [ \"office\" ], \"allocation\": 1}, {\"tags\": [ \"office\", \"project\" ], \"allocation\": 1}]";
+        let mut workgroups = testutil::workgroups(s);
+
+        let s = "{\"id\":3,\"start\":\"20210722T152328Z\",\"end\":\"20210722T152330Z\",\"tags\":[\"office\",\"project\"]}";
+        let jv = json::parse(s).unwrap();
+        let twe = super::TimeWarriorEntry::parse_from_json_value(&jv, &Clock::system()).unwrap();
+        process_entries(&[twe], &mut workgroups);
+
+        assert_eq!(workgroups[0].time_spent.num_seconds(), 2);
+        assert_eq!(workgroups[1].time_spent.num_seconds(), 0);
+    }
+
+    #[test]
+    fn check_json_value() {
+        let jv = json::parse("{\"tags\": [ \"office\" ], \"allocation\": 1}").unwrap();
+        assert!(WorkGroup::check_json_value(&jv).is_empty());
+
+        let jv = json::parse("{\"tags\": [], \"allocation\": \"2\"}").unwrap();
+        assert_eq!(
+            WorkGroup::check_json_value(&jv),
+            vec!["'allocation' \"2\" is not a number"]
+        );
+
+        let jv = json::parse("{\"tag\": [ \"office\" ]}").unwrap();
+        assert_eq!(
+            WorkGroup::check_json_value(&jv),
+            vec![
+                "'tags' is missing or is not a list",
+                "'allocation' is missing"
+            ]
+        );
+    }
+
+    #[test]
+    fn with_allocations_keeps_unknown_keys() {
+        let s = "[{\"tags\": [\"office\"], \"allocation\": 1, \"note\": \"meetings\"}, {\"tags\": [\"personal\"], \"allocation\": 2}]";
+        let mut workgroups = parse_allocation("/allocation/2021/7/29.json", s).unwrap();
+        workgroups[0].time_allocated = Duration::minutes(90);
+        let jv = json::parse(&with_allocations(s, &workgroups)).unwrap();
+        assert_eq!(jv[0]["allocation"], 1.5);
+        assert_eq!(jv[0]["note"], "meetings");
+        assert_eq!(jv[1]["allocation"], 2.0);
+
+        // A file which doesn't match the workgroups is written from scratch.
+        let jv = json::parse(&with_allocations("[]", &workgroups)).unwrap();
+        assert_eq!(jv.len(), 2);
+        assert!(jv[0]["note"].is_null());
+    }
+
+    #[test]
+    fn parse_allocation_invalid_group() {
+        let s = "[{\"tags\": [ \"office\" ], \"allocation\": 1}, {\"tags\": [ \"office\" ]}]";
+        let err = parse_allocation("/allocation/2021/7/29.json", s)
+            .err()
+            .unwrap();
+        assert!(err.starts_with("Invalid group 2 in the json file at /allocation/2021/7/29.json"));
+    }
+
+    #[test]
+    fn parse_allocation_catch_all() {
+        // A group with empty tags matches every interval left over by the groups before it.
+        let s =
+            "[{\"tags\": [ \"office\" ], \"allocation\": 1}, {\"tags\": [], \"allocation\": 2}]";
+        let workgroups = parse_allocation("/allocation/2021/7/29.json", s).unwrap();
+        assert_eq!(workgroups.len(), 2);
+        assert!(workgroups[1].tagset.tags.is_empty());
+    }
 }